@@ -5,14 +5,33 @@
 
 mod state;
 
+use std::collections::{BTreeMap, BTreeSet};
+
 use linera_sdk::{
-    linera_base_types::{WithContractAbi, ChainOwnership, ApplicationPermissions, Amount},
+    linera_base_types::{
+        Account, AccountOwner, WithContractAbi, ChainOwnership, ApplicationPermissions, Amount, StreamName,
+    },
     views::{RootView, View},
     Contract, ContractRuntime,
 };
-use meal_voting::{MealVotingAbi, Message, Operation};
+use meal_voting::{
+    BallotLimitPolicy, DedupStrategy, MealVotingAbi, Message, Operation, PollConfig, PollEvent, NONE_OF_THE_ABOVE_ID,
+    TallyMethod, POLL_EVENTS_STREAM,
+};
+
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use self::state::{AuditEntry, Comment, Nomination, PollState, RejectedBallot, ResultEntry};
 
-use self::state::{Nomination, PollState, ResultEntry};
+/// Maximum length of a nomination comment, in characters.
+const MAX_COMMENT_LENGTH: usize = 280;
+
+/// Maximum number of participant messages (nominations and votes) a single origin chain may
+/// deliver per throttling window.
+const MAX_MESSAGES_PER_WINDOW: u32 = 20;
+/// Length of the message throttling window, in microseconds.
+const MESSAGE_WINDOW_MICROS: u64 = 60_000_000;
 
 pub struct MealVotingContract {
     state: PollState,
@@ -21,6 +40,111 @@ pub struct MealVotingContract {
 
 linera_sdk::contract!(MealVotingContract);
 
+/// Why an operation or message was refused. Both entry points still abort the block on an error,
+/// using its message.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MealVotingError {
+    #[error("Results are finalized")]
+    ResultsFinalized,
+    #[error("Poll is cancelled")]
+    PollCancelled,
+    #[error("Poll is paused")]
+    PollPaused,
+    #[error("Poll is not paused")]
+    PollNotPaused,
+    #[error("Poll is already closed")]
+    PollClosed,
+    #[error("Poll is not closed yet")]
+    PollNotClosed,
+    #[error("Poll not yet open")]
+    PollNotOpen,
+    #[error("Voting has not started yet")]
+    VotingNotStarted,
+    #[error("Cannot start voting without any nominations")]
+    EmptyPoll,
+    #[error("Cannot join after voting has started")]
+    JoiningClosed,
+    #[error("Cannot nominate after voting has started")]
+    NominatingClosed,
+    #[error("User not in poll")]
+    NotAParticipant,
+    #[error("Failed to record participant: {0}")]
+    JoinFailed(String),
+    #[error("Control messages must come from the chain that created the poll")]
+    UntrustedOrigin,
+    #[error("This message must come from another chain")]
+    MissingOrigin,
+    #[error("This message needs an authenticated signer")]
+    MissingSigner,
+    #[error("Poll is already initialized")]
+    AlreadyInitialized,
+    #[error("Partial results must come from a registered shard chain")]
+    UnregisteredShard,
+    #[error("Poll is full: at most {0} participants")]
+    PollFull(u32),
+    #[error("Cannot remove participants after voting has started")]
+    RemovalClosed,
+    #[error("The admin cannot be removed")]
+    CannotRemoveAdmin,
+    /// Names the admin-only action that was attempted, e.g. `"close the poll"`.
+    #[error("Only admin can {0}")]
+    NotAdmin(&'static str),
+    #[error("Only the nominator can {0}")]
+    NotNominator(&'static str),
+    #[error("Unknown nomination: {0}")]
+    UnknownNomination(String),
+    #[error("Duplicate of nomination {0}")]
+    DuplicateNomination(String),
+    #[error("Nomination limit of {0} per participant reached")]
+    NominationLimitReached(u32),
+    #[error("Nomination already endorsed by this user")]
+    AlreadyEndorsed,
+    #[error("Comments must be between 1 and {} characters", MAX_COMMENT_LENGTH)]
+    InvalidComment,
+    #[error("Too many rankings. Max allowed: {0}")]
+    TooManyRankings(usize),
+    #[error("Ballot must rank at least {required} nominations, got {ranked}")]
+    IncompleteBallot { required: usize, ranked: usize },
+    #[error("Nomination {0} is ranked more than once")]
+    DuplicateRanking(String),
+    #[error("Ballot already cast and ballots are final")]
+    BallotsFinal,
+    #[error("No ballot to retract")]
+    NoBallot,
+    #[error("Cannot rank your own nomination")]
+    SelfRanking,
+    #[error("Cannot delegate your vote to yourself")]
+    SelfDelegation,
+    #[error("At most {picks} picks allowed in category {category}")]
+    CategoryBudgetExceeded { category: String, picks: u32 },
+    #[error("Nomination {0} is not on the shortlist")]
+    NotShortlisted(String),
+    #[error("Ballot must rank {required} nominations, got {ranked}")]
+    IncompleteRanking { required: usize, ranked: usize },
+    #[error("Allocations must total exactly {budget} points, got {total}")]
+    WrongPointTotal { budget: u64, total: u64 },
+    #[error("At most {0} points may go to a single nomination")]
+    TooManyPoints(u64),
+    #[error("Ratings must be between 1 and 5 stars, got {0}")]
+    InvalidRating(u8),
+    #[error("Votes per voter must be at least 1")]
+    ZeroVotesPerVoter,
+    #[error("Invalid poll configuration: {0}")]
+    InvalidConfiguration(&'static str),
+    #[error("Cannot change the point budget after point ballots were cast")]
+    PointBudgetLocked,
+    #[error("Existing ballots exceed the new limit of {0}")]
+    BallotsExceedLimit(u32),
+    #[error("No tie in the current results")]
+    NoTie,
+    #[error("Quorum not met: {voted} of {required} weight voted")]
+    QuorumNotMet { voted: u64, required: u64 },
+    #[error("No results to pay out")]
+    NoResults,
+    #[error("Winning nomination has no payout address")]
+    NoPayoutAddress,
+}
+
 impl WithContractAbi for MealVotingContract {
     type Abi = MealVotingAbi;
 }
@@ -29,7 +153,7 @@ impl Contract for MealVotingContract {
     type Message = Message;
     type InstantiationArgument = ();
     type Parameters = ();
-    type EventValue = ();
+    type EventValue = PollEvent;
 
     async fn load(runtime: ContractRuntime<Self>) -> Self {
         let state = PollState::load(runtime.root_view_storage_context())
@@ -44,199 +168,3797 @@ impl Contract for MealVotingContract {
     }
 
     async fn execute_operation(&mut self, operation: Operation) -> Self::Response {
+        if let Err(error) = self.try_execute_operation(operation).await {
+            panic!("{}", error);
+        }
+    }
+
+    async fn execute_message(&mut self, message: Message) {
+        if let Err(error) = self.try_execute_message(message).await {
+            panic!("{}", error);
+        }
+    }
+
+    async fn store(mut self) {
+        self.state.save().await.expect("Failed to save state");
+    }
+}
+
+impl MealVotingContract {
+    /// Execute an operation, returning why it was refused. `execute_operation` aborts on error.
+    async fn try_execute_operation(&mut self, operation: Operation) -> Result<(), MealVotingError> {
         let signer = self.runtime.authenticated_signer();
-        println!("EXECUTE_OPERATION: {:?}", operation);
+        let operation_count = *self.state.operation_count.get() + 1;
+        self.state.operation_count.set(operation_count);
+        if *self.state.finalized.get() {
+            return Err(MealVotingError::ResultsFinalized);
+        }
+        if *self.state.is_cancelled.get() {
+            return Err(MealVotingError::PollCancelled);
+        }
+        if *self.state.paused.get() && operation.owner() != self.state.admin_id.get() {
+            return Err(MealVotingError::PollPaused);
+        }
+        self.state.audit_log.push(AuditEntry {
+            operation_type: operation.kind().to_string(),
+            user_id: operation.owner().to_string(),
+            timestamp: self.runtime.system_time(),
+        });
 
         match operation {
-            Operation::CreatePoll { topic, votes_per_voter, owner } => {
-                let user_id = owner;
-                
+            Operation::CreatePoll { topic, votes_per_voter, owner, config } => {
+                if votes_per_voter == 0 {
+                    return Err(MealVotingError::ZeroVotesPerVoter);
+                }
+                if topic.trim().is_empty() {
+                    return Err(MealVotingError::InvalidConfiguration("topic must not be empty"));
+                }
+                validate_config(votes_per_voter, &config)?;
                 let owner_id = signer.expect("Needs authenticated signer to create poll");
-                
-                // Spawn a new microchain
-                let new_chain_id = self.runtime.open_chain(
-                    ChainOwnership::single(owner_id),
-                    ApplicationPermissions::default(),
-                    Amount::from_tokens(10),
-                );
-
-                // Send initialization message to the new chain
-                let msg = Message::InitializePoll {
-                    topic,
-                    votes_per_voter,
-                    admin_id: user_id.clone(),
-                };
-                self.runtime.prepare_message(msg).send_to(new_chain_id);
-
-                // Track created poll for the user
-                let mut polls = self.state.created_polls.get(&user_id).await.expect("get failed").unwrap_or_default();
-                polls.push(new_chain_id);
-                self.state.created_polls.insert(&user_id, polls).expect("insert failed");
+                self.open_poll_chain(owner_id, owner, topic, votes_per_voter, config, Vec::new()).await;
+            }
+            Operation::ClonePoll { source_chain, owner } => {
+                // Chains cannot read each other's state directly: ask the source poll for its
+                // topic and nominations, and open the new chain once `CloneData` comes back.
+                signer.expect("Needs authenticated signer to clone poll");
+                self.runtime
+                    .prepare_message(Message::RequestClone { owner })
+                    .with_authentication()
+                    .send_to(source_chain);
             }
             Operation::Join { name, owner } => {
-                println!("JOIN: User={}, Name={}", owner, name);
                 let user_id = owner;
                 if *self.state.is_closed.get() {
-                    panic!("Poll is closed");
+                    return Err(MealVotingError::PollClosed);
+                }
+                if *self.state.has_started.get() && !*self.state.late_join_allowed.get() {
+                    return Err(MealVotingError::JoiningClosed);
+                }
+                self.ensure_open()?;
+                self.ensure_room_for(&user_id).await?;
+                if self.state.participants.contains_key(&user_id).await.expect("contains failed") {
+                    self.emit_event(PollEvent::ParticipantRejoined { user_id });
+                    return Ok(());
+                }
+                self.state
+                    .participants
+                    .insert(&user_id, name)
+                    .map_err(|error| MealVotingError::JoinFailed(error.to_string()))?;
+                if let Some(creator_chain) = *self.state.creator_chain.get() {
+                    self.runtime
+                        .prepare_message(Message::JoinedPoll { user_id: user_id.clone() })
+                        .send_to(creator_chain);
                 }
-                match self.state.participants.insert(&user_id, name) {
-                    Ok(_) => println!("JOIN SUCESS"),
-                    Err(e) => panic!("JOIN FAILED: {:?}", e),
+                self.emit_event(PollEvent::ParticipantJoined { user_id });
+            }
+            Operation::Rename { name, owner } => {
+                if !self.state.participants.contains_key(&owner).await.expect("contains failed") {
+                    return Err(MealVotingError::NotAParticipant);
                 }
+                self.state.participants.insert(&owner, name.clone()).expect("insert failed");
+                self.emit_event(PollEvent::ParticipantRenamed { user_id: owner, name });
             }
-            Operation::Nominate { text, owner } => {
+            Operation::Nominate { text, category, tags, owner } => {
                 let user_id = owner;
                 if *self.state.has_started.get() {
-                    panic!("Cannot nominate after voting has started");
+                    return Err(MealVotingError::NominatingClosed);
                 }
+                self.ensure_open()?;
                 if !self.state.participants.contains_key(&user_id).await.expect("contains failed") {
-                    panic!("User not in poll");
+                    return Err(MealVotingError::NotAParticipant);
+                }
+                if !self.accept_duplicate(&user_id, &text).await? {
+                    return Ok(());
                 }
-                let nomination_id = format!("nom_{}", self.state.nominations.count().await.unwrap_or(0));
+                self.ensure_nomination_allowance(&user_id, 1).await?;
+                let nomination_id = self.allocate_nomination_id();
                 let nomination = Nomination {
                     user_id: user_id.clone(),
                     text,
+                    base_score: 0,
+                    category,
+                    tags: normalize_tags(tags),
                 };
                 self.state.nominations.insert(&nomination_id, nomination).expect("insert failed");
             }
             Operation::Vote { rankings, owner } => {
                 let user_id = owner;
                 if !*self.state.has_started.get() {
-                    panic!("Voting has not started yet");
+                    return Err(MealVotingError::VotingNotStarted);
+                }
+                if self.close_if_deadline_passed().await {
+                    return Ok(());
+                }
+                if *self.state.is_closed.get() {
+                    return Err(MealVotingError::PollClosed);
+                }
+                self.ensure_open()?;
+                if !self.state.participants.contains_key(&user_id).await.expect("contains failed") {
+                    return Err(MealVotingError::NotAParticipant);
+                }
+                if *self.state.ballots_final.get() && self.state.rankings.contains_key(&user_id).await.expect("contains failed") {
+                    return Err(MealVotingError::BallotsFinal);
+                }
+                self.submit_ballot(&user_id, rankings).await?;
+            }
+            Operation::VotePoints { allocations, owner } => {
+                let user_id = owner;
+                if !*self.state.has_started.get() {
+                    return Err(MealVotingError::VotingNotStarted);
+                }
+                if self.close_if_deadline_passed().await {
+                    return Ok(());
+                }
+                if *self.state.is_closed.get() {
+                    return Err(MealVotingError::PollClosed);
+                }
+                self.ensure_open()?;
+                if !self.state.participants.contains_key(&user_id).await.expect("contains failed") {
+                    return Err(MealVotingError::NotAParticipant);
+                }
+                if *self.state.ballots_final.get()
+                    && self.state.point_allocations.contains_key(&user_id).await.expect("contains failed")
+                {
+                    return Err(MealVotingError::BallotsFinal);
+                }
+                // The point budget is the same `votes_per_voter` allowance used by ranked ballots.
+                let budget = *self.state.votes_per_voter.get() as u64;
+                let total = allocations.iter().map(|(_, points)| *points).fold(0u64, u64::saturating_add);
+                if total != budget {
+                    return Err(MealVotingError::WrongPointTotal { budget, total });
+                }
+                let max_per_option = *self.state.max_points_per_option.get();
+                for (i, (nomination_id, points)) in allocations.iter().enumerate() {
+                    // Splitting one option's points over several entries would dodge the cap.
+                    if allocations[..i].iter().any(|(allocated, _)| allocated == nomination_id) {
+                        return Err(MealVotingError::DuplicateRanking(nomination_id.clone()));
+                    }
+                    if let Some(cap) = max_per_option {
+                        if *points > cap {
+                            return Err(MealVotingError::TooManyPoints(cap));
+                        }
+                    }
+                    if !self.state.nominations.contains_key(nomination_id).await.expect("contains failed") {
+                        return Err(MealVotingError::UnknownNomination(nomination_id.clone()));
+                    }
+                }
+                self.state.point_allocations.insert(&user_id, allocations).expect("insert failed");
+                self.close_if_threshold_reached().await;
+            }
+            Operation::RateOptions { ratings, owner } => {
+                let user_id = owner;
+                if !*self.state.has_started.get() {
+                    return Err(MealVotingError::VotingNotStarted);
                 }
+                if self.close_if_deadline_passed().await {
+                    return Ok(());
+                }
+                if *self.state.is_closed.get() {
+                    return Err(MealVotingError::PollClosed);
+                }
+                self.ensure_open()?;
+                if !self.state.participants.contains_key(&user_id).await.expect("contains failed") {
+                    return Err(MealVotingError::NotAParticipant);
+                }
+                if *self.state.ballots_final.get()
+                    && self.state.star_ratings.contains_key(&user_id).await.expect("contains failed")
+                {
+                    return Err(MealVotingError::BallotsFinal);
+                }
+                for (i, (nomination_id, stars)) in ratings.iter().enumerate() {
+                    if !(1..=5).contains(stars) {
+                        return Err(MealVotingError::InvalidRating(*stars));
+                    }
+                    if ratings[..i].iter().any(|(rated, _)| rated == nomination_id) {
+                        return Err(MealVotingError::DuplicateRanking(nomination_id.clone()));
+                    }
+                    if !self.state.nominations.contains_key(nomination_id).await.expect("contains failed") {
+                        return Err(MealVotingError::UnknownNomination(nomination_id.clone()));
+                    }
+                }
+                self.state.star_ratings.insert(&user_id, ratings).expect("insert failed");
+                self.close_if_threshold_reached().await;
+            }
+            Operation::EndorseNomination { nomination_id, owner } => {
+                let user_id = owner;
                 if *self.state.is_closed.get() {
-                    panic!("Poll is already closed");
+                    return Err(MealVotingError::PollClosed);
+                }
+                if !self.state.participants.contains_key(&user_id).await.expect("contains failed") {
+                    return Err(MealVotingError::NotAParticipant);
+                }
+                if !self.state.nominations.contains_key(&nomination_id).await.expect("contains failed") {
+                    return Err(MealVotingError::UnknownNomination(nomination_id.clone()));
                 }
-                let max_votes = *self.state.votes_per_voter.get() as usize;
-                if rankings.len() > max_votes {
-                    panic!("Too many rankings. Max allowed: {}", max_votes);
+                let mut endorsers = self.state.endorsements.get(&nomination_id).await.expect("get failed").unwrap_or_default();
+                if endorsers.contains(&user_id) {
+                    return Err(MealVotingError::AlreadyEndorsed);
+                }
+                endorsers.push(user_id);
+                self.state.endorsements.insert(&nomination_id, endorsers).expect("insert failed");
+            }
+            Operation::CommentOnNomination { nomination_id, text, owner } => {
+                let user_id = owner;
+                if *self.state.is_closed.get() {
+                    return Err(MealVotingError::PollClosed);
                 }
                 if !self.state.participants.contains_key(&user_id).await.expect("contains failed") {
-                    panic!("User not in poll");
+                    return Err(MealVotingError::NotAParticipant);
                 }
-                self.state.rankings.insert(&user_id, rankings).expect("insert failed");
+                if !self.state.nominations.contains_key(&nomination_id).await.expect("contains failed") {
+                    return Err(MealVotingError::UnknownNomination(nomination_id.clone()));
+                }
+                let text = text.trim().to_string();
+                if text.is_empty() || text.chars().count() > MAX_COMMENT_LENGTH {
+                    return Err(MealVotingError::InvalidComment);
+                }
+                let mut comments = self.state.comments.get(&nomination_id).await.expect("get failed").unwrap_or_default();
+                comments.push(Comment { user_id, text });
+                self.state.comments.insert(&nomination_id, comments).expect("insert failed");
             }
             Operation::StartVote { owner } => {
+                self.start_vote(&owner).await?;
+            }
+            Operation::ClosePoll { owner } => {
                 let user_id = owner;
                 if user_id != *self.state.admin_id.get() {
-                    panic!("Only admin can start voting");
+                    return Err(MealVotingError::NotAdmin("close the poll"));
                 }
-                self.state.has_started.set(true);
+                if *self.state.is_closed.get() {
+                    return Err(MealVotingError::PollClosed);
+                }
+                self.ensure_quorum().await?;
+                self.close_poll().await;
             }
-            Operation::ClosePoll { owner } => {
+            Operation::ReopenPoll { owner } => {
+                if owner != *self.state.admin_id.get() {
+                    return Err(MealVotingError::NotAdmin("reopen the poll"));
+                }
+                if !*self.state.is_closed.get() {
+                    return Err(MealVotingError::PollNotClosed);
+                }
+                self.state.is_closed.set(false);
+                self.state.closed_at.set(None);
+                self.state.results.set(Vec::new());
+                self.state.no_acceptable_option.set(false);
+                self.state.condorcet_winner.set(None);
+                self.state.quorum_met.set(false);
+            }
+            Operation::FinalizeResults { owner } => {
+                if owner != *self.state.admin_id.get() {
+                    return Err(MealVotingError::NotAdmin("finalize the results"));
+                }
+                if !*self.state.is_closed.get() {
+                    return Err(MealVotingError::PollNotClosed);
+                }
+                self.state.finalized.set(true);
+            }
+            Operation::SetVotesPerVoter { votes_per_voter, owner } => {
                 let user_id = owner;
                 if user_id != *self.state.admin_id.get() {
-                    panic!("Only admin can close the poll");
+                    return Err(MealVotingError::NotAdmin("change votes per voter"));
                 }
                 if *self.state.is_closed.get() {
-                    panic!("Poll is already closed");
+                    return Err(MealVotingError::PollClosed);
                 }
-                self.state.is_closed.set(true);
-                self.compute_results().await;
+                if votes_per_voter == 0 {
+                    return Err(MealVotingError::ZeroVotesPerVoter);
+                }
+                // Truncated ballots would otherwise end up shorter than `min_rankings` allows.
+                if votes_per_voter < *self.state.min_rankings.get() {
+                    return Err(MealVotingError::InvalidConfiguration("min rankings must not exceed votes per voter"));
+                }
+                self.reconcile_ballots(votes_per_voter).await?;
+                self.state.votes_per_voter.set(votes_per_voter);
+            }
+            Operation::RerollTieBreak { owner } => {
+                let user_id = owner;
+                if user_id != *self.state.admin_id.get() {
+                    return Err(MealVotingError::NotAdmin("re-draw the tie-break"));
+                }
+                if !*self.state.is_closed.get() {
+                    return Err(MealVotingError::PollNotClosed);
+                }
+                let mut results = self.state.results.get().clone();
+                if !results.windows(2).any(|pair| pair[0].score == pair[1].score) {
+                    return Err(MealVotingError::NoTie);
+                }
+                let seed = self.draw_tie_break_seed();
+                self.state.tie_break_seed.set(Some(seed));
+                sort_results(&mut results, Some(seed));
+                self.publish_results(results);
+            }
+            Operation::SetPayoutAddress { nomination_id, address, owner } => {
+                let user_id = owner;
+                let nomination = self
+                    .state
+                    .nominations
+                    .get(&nomination_id)
+                    .await
+                    .expect("get failed")
+                    .ok_or_else(|| MealVotingError::UnknownNomination(nomination_id.clone()))?;
+                if nomination.user_id != user_id {
+                    return Err(MealVotingError::NotNominator("set the payout address"));
+                }
+                self.state.payout_addresses.insert(&nomination_id, address).expect("insert failed");
+            }
+            Operation::PausePoll { owner } => {
+                if owner != *self.state.admin_id.get() {
+                    return Err(MealVotingError::NotAdmin("pause the poll"));
+                }
+                if *self.state.is_closed.get() {
+                    return Err(MealVotingError::PollClosed);
+                }
+                self.state.paused.set(true);
+            }
+            Operation::ResumePoll { owner } => {
+                if owner != *self.state.admin_id.get() {
+                    return Err(MealVotingError::NotAdmin("resume the poll"));
+                }
+                if !*self.state.paused.get() {
+                    return Err(MealVotingError::PollNotPaused);
+                }
+                self.state.paused.set(false);
+            }
+            Operation::CollectResults { chain_ids, owner: _ } => {
+                // Other chains' state can't be read directly: each poll chain answers with a
+                // `ReportResults` message, stored in `poll_results` for `aggregate_results`.
+                for chain_id in chain_ids {
+                    self.runtime.prepare_message(Message::RequestResults).send_to(chain_id);
+                }
+            }
+            Operation::AllowLateJoin { owner } => {
+                if owner != *self.state.admin_id.get() {
+                    return Err(MealVotingError::NotAdmin("change late joining"));
+                }
+                if *self.state.is_closed.get() {
+                    return Err(MealVotingError::PollClosed);
+                }
+                let allowed = !*self.state.late_join_allowed.get();
+                self.state.late_join_allowed.set(allowed);
+            }
+            Operation::SetShortlist { nomination_ids, owner } => {
+                if owner != *self.state.admin_id.get() {
+                    return Err(MealVotingError::NotAdmin("set the shortlist"));
+                }
+                if *self.state.is_closed.get() {
+                    return Err(MealVotingError::PollClosed);
+                }
+                for nomination_id in &nomination_ids {
+                    if !self.state.nominations.contains_key(nomination_id).await.expect("contains failed") {
+                        return Err(MealVotingError::UnknownNomination(nomination_id.clone()));
+                    }
+                }
+                self.state.shortlist.set(nomination_ids);
+            }
+            Operation::SetBaseScore { nomination_id, base_score, owner } => {
+                if owner != *self.state.admin_id.get() {
+                    return Err(MealVotingError::NotAdmin("set base scores"));
+                }
+                if *self.state.is_closed.get() {
+                    return Err(MealVotingError::PollClosed);
+                }
+                let mut nomination = self
+                    .state
+                    .nominations
+                    .get(&nomination_id)
+                    .await
+                    .expect("get failed")
+                    .ok_or_else(|| MealVotingError::UnknownNomination(nomination_id.clone()))?;
+                nomination.base_score = base_score;
+                self.state.nominations.insert(&nomination_id, nomination).expect("insert failed");
+            }
+            Operation::SetParticipantWeight { user_id, weight, owner } => {
+                if owner != *self.state.admin_id.get() {
+                    return Err(MealVotingError::NotAdmin("set participant weights"));
+                }
+                if *self.state.is_closed.get() {
+                    return Err(MealVotingError::PollClosed);
+                }
+                if !self.state.participants.contains_key(&user_id).await.expect("contains failed") {
+                    return Err(MealVotingError::NotAParticipant);
+                }
+                self.state.participant_weights.insert(&user_id, weight).expect("insert failed");
+            }
+            Operation::PayoutWinner { owner } => {
+                let user_id = owner;
+                if user_id != *self.state.admin_id.get() {
+                    return Err(MealVotingError::NotAdmin("pay out the winner"));
+                }
+                if !*self.state.is_closed.get() {
+                    return Err(MealVotingError::PollNotClosed);
+                }
+                let recipient = self.payout_recipient().await?;
+                let balance = self.runtime.chain_balance();
+                self.runtime.transfer(AccountOwner::CHAIN, recipient, balance);
+            }
+            Operation::RemoveParticipant { target, owner } => {
+                self.remove_participant(&owner, &target).await?;
+            }
+            Operation::ReopenNominations { owner } => {
+                self.reopen_nominations(&owner)?;
+            }
+            Operation::TransferAdmin { new_admin, owner } => {
+                self.transfer_admin(&owner, new_admin).await?;
+            }
+            Operation::CancelPoll { owner } => {
+                self.cancel_poll(&owner)?;
+            }
+            Operation::SubmitPartialResults { coordinator, owner } => {
+                if owner != *self.state.admin_id.get() {
+                    return Err(MealVotingError::NotAdmin("submit partial results"));
+                }
+                if !*self.state.is_closed.get() {
+                    return Err(MealVotingError::PollNotClosed);
+                }
+                let scores = self.state.results.get().clone();
+                self.runtime.prepare_message(Message::SubmitPartialResults { scores }).send_to(coordinator);
+            }
+            Operation::RegisterShard { shard, owner } => {
+                if owner != *self.state.admin_id.get() {
+                    return Err(MealVotingError::NotAdmin("register shards"));
+                }
+                let mut shards = self.state.shard_chains.get().clone();
+                if !shards.contains(&shard) {
+                    shards.push(shard);
+                    self.state.shard_chains.set(shards);
+                }
+            }
+            Operation::NominateBatch { texts, owner } => {
+                if *self.state.has_started.get() {
+                    return Err(MealVotingError::NominatingClosed);
+                }
+                self.ensure_open()?;
+                if !self.state.participants.contains_key(&owner).await.expect("contains failed") {
+                    return Err(MealVotingError::NotAParticipant);
+                }
+                self.nominate_batch(&owner, texts).await?;
+            }
+            Operation::Delegate { to, owner } => {
+                if *self.state.is_closed.get() {
+                    return Err(MealVotingError::PollClosed);
+                }
+                if to == owner {
+                    return Err(MealVotingError::SelfDelegation);
+                }
+                for user_id in [&owner, &to] {
+                    if !self.state.participants.contains_key(user_id).await.expect("contains failed") {
+                        return Err(MealVotingError::NotAParticipant);
+                    }
+                }
+                self.state.delegations.insert(&owner, to).expect("insert failed");
+            }
+            Operation::EditNomination { nomination_id, new_text, owner } => {
+                if *self.state.has_started.get() {
+                    return Err(MealVotingError::NominatingClosed);
+                }
+                let mut nomination = self
+                    .state
+                    .nominations
+                    .get(&nomination_id)
+                    .await
+                    .expect("get failed")
+                    .ok_or_else(|| MealVotingError::UnknownNomination(nomination_id.clone()))?;
+                if nomination.user_id != owner {
+                    return Err(MealVotingError::NotNominator("edit the nomination"));
+                }
+                // An edit can't be merged away like a new nomination, so any clash is refused
+                // unless duplicates are allowed outright.
+                if *self.state.dedup_strategy.get() != DedupStrategy::Allow {
+                    if let Some((existing_id, _)) = self.find_duplicate(&new_text).await {
+                        if existing_id != nomination_id {
+                            return Err(MealVotingError::DuplicateNomination(existing_id));
+                        }
+                    }
+                }
+                nomination.text = new_text;
+                self.state.nominations.insert(&nomination_id, nomination).expect("insert failed");
+            }
+            Operation::RetractVote { owner } => {
+                let user_id = owner;
+                if !*self.state.has_started.get() {
+                    return Err(MealVotingError::VotingNotStarted);
+                }
+                if self.close_if_deadline_passed().await {
+                    return Ok(());
+                }
+                if *self.state.is_closed.get() {
+                    return Err(MealVotingError::PollClosed);
+                }
+                self.ensure_open()?;
+                if *self.state.ballots_final.get() {
+                    return Err(MealVotingError::BallotsFinal);
+                }
+                let ballot = self
+                    .state
+                    .rankings
+                    .get(&user_id)
+                    .await
+                    .expect("get failed")
+                    .ok_or(MealVotingError::NoBallot)?;
+                if ballot.is_empty() {
+                    let abstentions = *self.state.abstentions.get();
+                    self.state.abstentions.set(abstentions - 1);
+                }
+                self.state.rankings.remove(&user_id).expect("remove failed");
+                self.state.ranking_times.remove(&user_id).expect("remove failed");
             }
         }
+        Ok(())
     }
 
-    async fn execute_message(&mut self, message: Message) {
-        // Handle cross-chain messages from other chains
-        // Handle cross-chain messages
+    /// Execute a message, returning why it was refused. `execute_message` aborts on error.
+    async fn try_execute_message(&mut self, message: Message) -> Result<(), MealVotingError> {
+        // Handle cross-chain messages from other chains. Only participant traffic is throttled:
+        // notices, replies and reports between the app's own chains must never be dropped.
+        let from_participant =
+            matches!(message, Message::Nominate { .. } | Message::NominateBatch { .. } | Message::Vote { .. });
+        if from_participant && !self.within_message_rate_limit().await {
+            let dropped = *self.state.dropped_messages.get() + 1;
+            self.state.dropped_messages.set(dropped);
+            return Ok(());
+        }
+        // A cancelled poll can still be cloned or asked for its (empty) results.
+        if *self.state.is_cancelled.get()
+            && !matches!(message, Message::RequestClone { .. } | Message::RequestResults)
+        {
+            return Err(MealVotingError::PollCancelled);
+        }
+        // Finalized results are frozen: a finalized poll only answers requests for its data and
+        // keeps the factory-side bookkeeping of polls it created.
+        if *self.state.finalized.get()
+            && !matches!(
+                message,
+                Message::RequestClone { .. }
+                    | Message::RequestResults
+                    | Message::CloneData { .. }
+                    | Message::ReportResults { .. }
+                    | Message::JoinedPoll { .. }
+            )
+        {
+            return Err(MealVotingError::ResultsFinalized);
+        }
+        // The admin ID in a control message is just a string any chain could guess, so these
+        // are only trusted from the chain that created the poll.
+        let is_control = matches!(
+            message,
+            Message::StartVote { .. }
+                | Message::ClosePoll { .. }
+                | Message::RemoveParticipant { .. }
+                | Message::ReopenNominations { .. }
+                | Message::TransferAdmin { .. }
+                | Message::CancelPoll { .. }
+        );
+        if is_control && self.runtime.message_origin_chain_id() != *self.state.creator_chain.get() {
+            return Err(MealVotingError::UntrustedOrigin);
+        }
         match message {
-            Message::InitializePoll { topic, votes_per_voter, admin_id } => {
+            Message::InitializePoll { topic, votes_per_voter, admin_id, config, nominations } => {
+                // A replayed initialization would hand the poll to whichever chain sent it.
+                if self.state.creator_chain.get().is_some() || !self.state.admin_id.get().is_empty() {
+                    return Err(MealVotingError::AlreadyInitialized);
+                }
                 self.state.topic.set(topic);
                 self.state.votes_per_voter.set(votes_per_voter);
+                self.state.max_points_per_option.set(config.max_points_per_option);
+                self.state.ballot_limit_policy.set(config.ballot_limit_policy);
+                self.state.ballots_final.set(config.ballots_final);
+                self.state.opens_at.set(config.opens_at);
+                self.state.fee_per_operation.set(config.fee_per_operation);
+                self.state.quorum_weight.set(config.quorum_weight);
+                self.state.category_budgets.set(config.category_budgets);
+                self.state.decision_deadline.set(config.decision_deadline);
+                self.state.dedup_strategy.set(config.dedup_strategy);
+                self.state.require_full_ranking.set(config.require_full_ranking);
+                self.state.winning_score_threshold.set(config.winning_score_threshold);
+                self.state.record_rejected_ballots.set(config.record_rejected_ballots);
+                self.state.tally_method.set(config.tally_method);
+                self.state.deadline_micros.set(config.deadline_micros);
+                self.state.max_participants.set(config.max_participants);
+                self.state.quorum.set(config.quorum);
+                self.state.description.set(config.description);
+                self.state.category.set(config.category);
+                self.state.min_rankings.set(config.min_rankings);
+                self.state.max_nominations_per_user.set(config.max_nominations_per_user);
+                self.state.multi_winner.set(config.multi_winner);
+                self.state.hide_ballots_until_close.set(config.hide_ballots_until_close);
+                self.state.borda_variant.set(config.borda_variant);
+                self.state.tie_break_seed.set(config.tie_break_seed);
+                self.state.allow_write_ins.set(config.allow_write_ins);
+                self.state.anonymous_ballots.set(config.anonymous_ballots);
+                let salt = self.draw_ballot_salt(&admin_id, &self.state.topic.get().clone());
+                self.state.ballot_salt.set(salt);
+                self.state.allow_self_ranking.set(config.allow_self_ranking);
+                self.state.creator_chain.set(self.runtime.message_origin_chain_id());
                 self.state.admin_id.set(admin_id.clone());
                 self.state.has_started.set(false);
                 self.state.is_closed.set(false);
                 self.state.results.set(Vec::new());
 
                 self.state.participants.insert(&admin_id, "Admin".to_string()).expect("insert failed");
+                for (user_id, text) in nominations {
+                    let nomination = Nomination { user_id, text, base_score: 0, category: None, tags: Vec::new() };
+                    let nomination_id = self.allocate_nomination_id();
+                    self.state.nominations.insert(&nomination_id, nomination).expect("insert failed");
+                }
+                if config.none_of_the_above {
+                    let nomination = Nomination { user_id: admin_id, text: "None of the above".to_string(), base_score: 0, category: None, tags: Vec::new() };
+                    self.state.nominations.insert(NONE_OF_THE_ABOVE_ID, nomination).expect("insert failed");
+                }
+                let topic = self.state.topic.get().clone();
+                self.emit_event(PollEvent::PollCreated { topic, admin_id: self.state.admin_id.get().clone() });
             }
-            Message::Nominate { user_id, text } => {
+            Message::RequestClone { owner } => {
+                let requester = self.runtime.message_origin_chain_id().ok_or(MealVotingError::MissingOrigin)?;
+                let mut nominations = Vec::new();
+                let indices = self.state.nominations.indices().await.expect("indices failed");
+                for nomination_id in indices {
+                    // Recreated by the clone's own `none_of_the_above` setting.
+                    if nomination_id == NONE_OF_THE_ABOVE_ID {
+                        continue;
+                    }
+                    if let Some(nomination) = self.state.nominations.get(&nomination_id).await.expect("get failed") {
+                        nominations.push((nomination.user_id, nomination.text));
+                    }
+                }
+                let data = Message::CloneData {
+                    topic: self.state.topic.get().clone(),
+                    votes_per_voter: *self.state.votes_per_voter.get(),
+                    config: Box::new(self.clone_config().await),
+                    nominations,
+                    owner,
+                };
+                self.runtime.prepare_message(data).with_authentication().send_to(requester);
+            }
+            Message::CloneData { topic, votes_per_voter, config, nominations, owner } => {
+                let owner_id = self.runtime.authenticated_signer().ok_or(MealVotingError::MissingSigner)?;
+                self.open_poll_chain(owner_id, owner, topic, votes_per_voter, *config, nominations).await;
+            }
+            Message::RequestResults => {
+                let requester = self.runtime.message_origin_chain_id().ok_or(MealVotingError::MissingOrigin)?;
+                let results = self.state.results.get().clone();
+                self.runtime.prepare_message(Message::ReportResults { results }).send_to(requester);
+            }
+            Message::ReportResults { results } => {
+                let poll_chain = self.runtime.message_origin_chain_id().ok_or(MealVotingError::MissingOrigin)?;
+                self.state.poll_results.insert(&poll_chain, results).expect("insert failed");
+            }
+            Message::SubmitPartialResults { scores } => {
+                let shard = self.runtime.message_origin_chain_id().ok_or(MealVotingError::MissingOrigin)?;
+                if !self.state.shard_chains.get().contains(&shard) {
+                    return Err(MealVotingError::UnregisteredShard);
+                }
+                // Keyed by shard, so a re-send replaces that shard's earlier submission.
+                self.state.partial_results.insert(&shard, scores).expect("insert failed");
+                self.compute_results().await;
+            }
+            Message::JoinedPoll { user_id } => {
+                let poll_chain = self.runtime.message_origin_chain_id().ok_or(MealVotingError::MissingOrigin)?;
+                let mut polls = self.state.joined_polls.get(&user_id).await.expect("get failed").unwrap_or_default();
+                if !polls.contains(&poll_chain) {
+                    polls.push(poll_chain);
+                    self.state.joined_polls.insert(&user_id, polls).expect("insert failed");
+                }
+            }
+            Message::Nominate { user_id, text, category, tags } => {
+                if *self.state.paused.get() {
+                    return Err(MealVotingError::PollPaused);
+                }
                 if *self.state.has_started.get() {
-                    panic!("Cannot nominate after voting has started");
+                    return Err(MealVotingError::NominatingClosed);
                 }
-                let nomination_id = format!("nom_{}", self.state.nominations.count().await.unwrap_or(0));
+                self.ensure_open()?;
+                if !self.accept_duplicate(&user_id, &text).await? {
+                    return Ok(());
+                }
+                self.ensure_nomination_allowance(&user_id, 1).await?;
+                let nomination_id = self.allocate_nomination_id();
                 let nomination = Nomination {
                     user_id: user_id.clone(),
                     text,
+                    base_score: 0,
+                    category,
+                    tags: normalize_tags(tags),
                 };
                 self.state.nominations.insert(&nomination_id, nomination).expect("insert failed");
             }
+            Message::NominateBatch { user_id, texts } => {
+                if *self.state.paused.get() {
+                    return Err(MealVotingError::PollPaused);
+                }
+                if *self.state.has_started.get() {
+                    return Err(MealVotingError::NominatingClosed);
+                }
+                self.ensure_open()?;
+                self.nominate_batch(&user_id, texts).await?;
+            }
             Message::Vote { user_id, rankings } => {
                 if !*self.state.has_started.get() {
-                    panic!("Voting has not started yet");
+                    return Err(MealVotingError::VotingNotStarted);
+                }
+                if self.close_if_deadline_passed().await {
+                    return Ok(());
                 }
                 if *self.state.is_closed.get() {
-                    panic!("Poll is already closed");
+                    return Err(MealVotingError::PollClosed);
                 }
-                let max_votes = *self.state.votes_per_voter.get() as usize;
-                if rankings.len() > max_votes {
-                    panic!("Too many rankings. Max allowed: {}", max_votes);
+                if *self.state.paused.get() {
+                    return Err(MealVotingError::PollPaused);
                 }
-                self.state.rankings.insert(&user_id, rankings).expect("insert failed");
+                self.ensure_open()?;
+                if *self.state.ballots_final.get() && self.state.rankings.contains_key(&user_id).await.expect("contains failed") {
+                    return Err(MealVotingError::BallotsFinal);
+                }
+                self.submit_ballot(&user_id, rankings).await?;
             }
             Message::StartVote { user_id } => {
-                if user_id != *self.state.admin_id.get() {
-                    panic!("Only admin can start voting");
-                }
-                self.state.has_started.set(true);
+                self.start_vote(&user_id).await?;
             }
             Message::ClosePoll { user_id } => {
                 if user_id != *self.state.admin_id.get() {
-                    panic!("Only admin can close the poll");
+                    return Err(MealVotingError::NotAdmin("close the poll"));
                 }
                 if *self.state.is_closed.get() {
-                    panic!("Poll is already closed");
+                    return Err(MealVotingError::PollClosed);
                 }
-                self.state.is_closed.set(true);
-                self.compute_results().await;
+                self.ensure_quorum().await?;
+                self.close_poll().await;
+            }
+            Message::RemoveParticipant { user_id, target } => {
+                self.remove_participant(&user_id, &target).await?;
+            }
+            Message::ReopenNominations { user_id } => {
+                self.reopen_nominations(&user_id)?;
+            }
+            Message::TransferAdmin { user_id, new_admin } => {
+                self.transfer_admin(&user_id, new_admin).await?;
+            }
+            Message::CancelPoll { user_id } => {
+                self.cancel_poll(&user_id)?;
             }
         }
+        Ok(())
     }
 
-    async fn store(mut self) {
-        self.state.save().await.expect("Failed to save state");
+    /// Hand out the next `nom_N` ID. IDs are never reused, even if nominations are dropped.
+    fn allocate_nomination_id(&mut self) -> String {
+        let next = *self.state.next_nomination_id.get();
+        self.state.next_nomination_id.set(next + 1);
+        format!("nom_{}", next)
     }
-}
 
-impl MealVotingContract {
-    /// Compute results using a simple Borda-like scoring.
-    async fn compute_results(&mut self) {
-        use std::collections::BTreeMap;
+    /// Undo `target`'s join on behalf of `user_id`, who must be the admin: drop the participant,
+    /// their nominations (with endorsements, comments and payout addresses) and any ballot.
+    async fn remove_participant(&mut self, user_id: &str, target: &str) -> Result<(), MealVotingError> {
+        let admin_id = self.state.admin_id.get().clone();
+        if user_id != admin_id {
+            return Err(MealVotingError::NotAdmin("remove participants"));
+        }
+        if *self.state.has_started.get() {
+            return Err(MealVotingError::RemovalClosed);
+        }
+        if target == admin_id {
+            return Err(MealVotingError::CannotRemoveAdmin);
+        }
+        if !self.state.participants.contains_key(target).await.expect("contains failed") {
+            return Err(MealVotingError::NotAParticipant);
+        }
+        self.state.participants.remove(target).expect("remove failed");
+        let indices = self.state.nominations.indices().await.expect("indices failed");
+        for nomination_id in indices {
+            let Some(nomination) = self.state.nominations.get(&nomination_id).await.expect("get failed") else {
+                continue;
+            };
+            if nomination.user_id == target {
+                self.state.nominations.remove(&nomination_id).expect("remove failed");
+                self.state.endorsements.remove(&nomination_id).expect("remove failed");
+                self.state.comments.remove(&nomination_id).expect("remove failed");
+                self.state.payout_addresses.remove(&nomination_id).expect("remove failed");
+            }
+        }
+        self.state.rankings.remove(target).expect("remove failed");
+        self.state.ranking_times.remove(target).expect("remove failed");
+        self.state.vote_revisions.remove(target).expect("remove failed");
+        self.state.delegations.remove(target).expect("remove failed");
+        self.state.point_allocations.remove(target).expect("remove failed");
+        self.state.star_ratings.remove(target).expect("remove failed");
+        Ok(())
+    }
+
+    /// Make the participant `new_admin` the admin on behalf of `user_id`, who must be the current
+    /// one. Every admin check reads `admin_id`, so the old admin loses their privileges at once.
+    async fn transfer_admin(&mut self, user_id: &str, new_admin: String) -> Result<(), MealVotingError> {
+        if user_id != self.state.admin_id.get() {
+            return Err(MealVotingError::NotAdmin("transfer the admin role"));
+        }
+        if !self.state.participants.contains_key(&new_admin).await.expect("contains failed") {
+            return Err(MealVotingError::NotAParticipant);
+        }
+        self.state.admin_id.set(new_admin);
+        Ok(())
+    }
 
-        let mut scores: BTreeMap<String, u64> = BTreeMap::new();
-        let max_votes = *self.state.votes_per_voter.get() as u64;
+    /// Abort the poll on behalf of `user_id`, who must be the admin. Unlike closing, no results
+    /// are computed; closed polls keep theirs and cannot be cancelled.
+    fn cancel_poll(&mut self, user_id: &str) -> Result<(), MealVotingError> {
+        if user_id != self.state.admin_id.get() {
+            return Err(MealVotingError::NotAdmin("cancel the poll"));
+        }
+        if *self.state.is_closed.get() {
+            return Err(MealVotingError::PollClosed);
+        }
+        self.state.is_cancelled.set(true);
+        self.state.results.set(Vec::new());
+        Ok(())
+    }
 
-        let rankings_keys = self.state.rankings.indices().await.expect("indices failed");
-        
-        for user_id in rankings_keys {
-            if let Some(user_rankings) = self.state.rankings.get(&user_id).await.expect("get failed") {
-                for (i, nomination_id) in user_rankings.iter().enumerate() {
-                    let points = max_votes.saturating_sub(i as u64);
-                    *scores.entry(nomination_id.clone()).or_insert(0) += points;
-                }
+    /// Go back from voting to nominating on behalf of `user_id`, who must be the admin. Ballots
+    /// were cast against the old nomination set, so all of them are dropped.
+    fn reopen_nominations(&mut self, user_id: &str) -> Result<(), MealVotingError> {
+        if user_id != self.state.admin_id.get() {
+            return Err(MealVotingError::NotAdmin("reopen nominations"));
+        }
+        if *self.state.is_closed.get() {
+            return Err(MealVotingError::PollClosed);
+        }
+        if !*self.state.has_started.get() {
+            return Err(MealVotingError::VotingNotStarted);
+        }
+        self.state.has_started.set(false);
+        self.state.rankings.clear();
+        self.state.ranking_times.clear();
+        self.state.vote_revisions.clear();
+        self.state.point_allocations.clear();
+        self.state.star_ratings.clear();
+        self.state.abstentions.set(0);
+        Ok(())
+    }
+
+    /// Validate a ranked ballot's contents. An empty ballot is an abstention and always valid.
+    async fn check_ballot(&self, user_id: &str, rankings: &[String]) -> Result<(), MealVotingError> {
+        if rankings.is_empty() {
+            return Ok(());
+        }
+        let max_votes = *self.state.votes_per_voter.get() as usize;
+        if rankings.len() > max_votes {
+            return Err(MealVotingError::TooManyRankings(max_votes));
+        }
+        let min_rankings = *self.state.min_rankings.get() as usize;
+        if rankings.len() < min_rankings {
+            return Err(MealVotingError::IncompleteBallot { required: min_rankings, ranked: rankings.len() });
+        }
+        for (i, nomination_id) in rankings.iter().enumerate() {
+            if rankings[..i].contains(nomination_id) {
+                return Err(MealVotingError::DuplicateRanking(nomination_id.clone()));
+            }
+            if !self.state.nominations.contains_key(nomination_id).await.expect("contains failed") {
+                return Err(MealVotingError::UnknownNomination(nomination_id.clone()));
             }
         }
+        self.check_not_self_ranked(user_id, rankings).await?;
+        self.check_category_budgets(rankings).await?;
+        self.check_shortlisted(rankings)?;
+        self.check_full_ranking(rankings).await
+    }
 
-        let mut results: Vec<ResultEntry> = Vec::new();
-        for (nomination_id, score) in scores {
-            let text = self
-                .state
-                .nominations
-                .get(&nomination_id)
-                .await
-                .expect("get failed")
-                .map(|n| n.text.clone())
-                .unwrap_or_else(|| "Unknown".to_string());
-            results.push(ResultEntry {
-                nomination_id,
-                nomination_text: text,
-                score,
-            });
+    /// Store a ranked ballot, keeping `abstentions` in step with the empty ballots among them.
+    async fn record_ballot(&mut self, user_id: &str, rankings: Vec<String>) {
+        let previous = self.state.rankings.get(user_id).await.expect("get failed");
+        let mut abstentions = *self.state.abstentions.get();
+        if previous.is_some_and(|ballot| ballot.is_empty()) {
+            abstentions -= 1;
+        }
+        if rankings.is_empty() {
+            abstentions += 1;
         }
+        self.state.abstentions.set(abstentions);
+        let now = self.runtime.system_time();
+        self.state.ranking_times.insert(user_id, now).expect("insert failed");
+        let revisions = self.state.vote_revisions.get(user_id).await.expect("get failed").unwrap_or(0);
+        self.state.vote_revisions.insert(user_id, revisions + 1).expect("insert failed");
+        self.state.rankings.insert(user_id, rankings).expect("insert failed");
+    }
 
-        results.sort_by(|a, b| b.score.cmp(&a.score));
+    /// Validate and store `user_id`'s ranked ballot, turning write-ins into nominations first.
+    /// Write-ins are dropped again if the ballot is refused.
+    async fn submit_ballot(&mut self, user_id: &str, rankings: Vec<String>) -> Result<(), MealVotingError> {
+        let (resolved, written_in) = self.add_write_ins(user_id, &rankings).await;
+        if let Err(error) = self.check_ballot(user_id, &resolved).await {
+            for nomination_id in written_in {
+                self.state.nominations.remove(&nomination_id).expect("remove failed");
+            }
+            return self.reject_ballot(user_id, rankings, error).await;
+        }
+        self.record_ballot(user_id, resolved).await;
+        self.close_if_threshold_reached().await;
+        Ok(())
+    }
 
-        self.state.results.set(results);
+    /// Under `allow_write_ins`, replace each ranking that isn't a nomination ID with the ID of the
+    /// nomination bearing that name, adding one by `user_id` if there is none. Returns the
+    /// resolved ballot and the IDs of the nominations added.
+    async fn add_write_ins(&mut self, user_id: &str, rankings: &[String]) -> (Vec<String>, Vec<String>) {
+        if !*self.state.allow_write_ins.get() {
+            return (rankings.to_vec(), Vec::new());
+        }
+        let mut resolved = Vec::with_capacity(rankings.len());
+        let mut written_in = Vec::new();
+        for entry in rankings {
+            let is_nomination = self.state.nominations.contains_key(entry).await.expect("contains failed");
+            if is_nomination || entry.trim().is_empty() {
+                resolved.push(entry.clone());
+                continue;
+            }
+            if let Some((nomination_id, _)) = self.find_duplicate(entry).await {
+                resolved.push(nomination_id);
+                continue;
+            }
+            let nomination_id = self.allocate_nomination_id();
+            let nomination = Nomination {
+                user_id: user_id.to_string(),
+                text: entry.trim().to_string(),
+                base_score: 0,
+                category: None,
+                tags: Vec::new(),
+            };
+            self.state.nominations.insert(&nomination_id, nomination).expect("insert failed");
+            written_in.push(nomination_id.clone());
+            resolved.push(nomination_id);
+        }
+        (resolved, written_in)
+    }
+
+    /// Fail the vote with `error`, or, under `record_rejected_ballots`, keep the ballot in
+    /// `rejected_ballots` instead. Recording must succeed, as failing would revert the record too.
+    async fn reject_ballot(
+        &mut self,
+        user_id: &str,
+        rankings: Vec<String>,
+        error: MealVotingError,
+    ) -> Result<(), MealVotingError> {
+        if !*self.state.record_rejected_ballots.get() {
+            return Err(error);
+        }
+        let mut rejected = self.state.rejected_ballots.get(user_id).await.expect("get failed").unwrap_or_default();
+        rejected.push(RejectedBallot {
+            rankings,
+            reason: error.to_string(),
+            timestamp: self.runtime.system_time(),
+        });
+        self.state.rejected_ballots.insert(user_id, rejected).expect("insert failed");
+        Ok(())
+    }
+
+    /// Under `require_full_ranking`, reject ballots that skip an active nomination (the shortlist,
+    /// or every nomination without one), as far as `votes_per_voter` allows. Checked against the
+    /// nominations at voting time, so it follows any changes to them.
+    async fn check_full_ranking(&self, rankings: &[String]) -> Result<(), MealVotingError> {
+        if !*self.state.require_full_ranking.get() {
+            return Ok(());
+        }
+        let shortlist = self.state.shortlist.get();
+        let active = if shortlist.is_empty() {
+            self.state.nominations.indices().await.expect("indices failed")
+        } else {
+            shortlist.clone()
+        };
+        let required = active.len().min(*self.state.votes_per_voter.get() as usize);
+        let ranked = active.iter().filter(|id| rankings.contains(id)).count();
+        if ranked < required {
+            return Err(MealVotingError::IncompleteRanking { required, ranked });
+        }
+        Ok(())
+    }
+
+    /// Apply the dedup strategy to a new nomination's text: `false` if it should not be stored
+    /// because it merges into an existing nomination, which `user_id` then endorses. Fails under
+    /// `Reject`.
+    async fn accept_duplicate(&mut self, user_id: &str, text: &str) -> Result<bool, MealVotingError> {
+        let strategy = *self.state.dedup_strategy.get();
+        if strategy == DedupStrategy::Allow {
+            return Ok(true);
+        }
+        let Some((nomination_id, nomination)) = self.find_duplicate(text).await else {
+            return Ok(true);
+        };
+        if strategy == DedupStrategy::Reject {
+            return Err(MealVotingError::DuplicateNomination(nomination_id));
+        }
+        let mut endorsers = self.state.endorsements.get(&nomination_id).await.expect("get failed").unwrap_or_default();
+        if nomination.user_id != user_id && !endorsers.iter().any(|endorser| endorser == user_id) {
+            endorsers.push(user_id.to_string());
+            self.state.endorsements.insert(&nomination_id, endorsers).expect("insert failed");
+        }
+        Ok(false)
+    }
+
+    /// The existing nomination whose text matches `text`, ignoring case and surrounding whitespace.
+    async fn find_duplicate(&self, text: &str) -> Option<(String, Nomination)> {
+        let normalized = text.trim().to_lowercase();
+        let indices = self.state.nominations.indices().await.expect("indices failed");
+        for nomination_id in indices {
+            let Some(nomination) = self.state.nominations.get(&nomination_id).await.expect("get failed") else {
+                continue;
+            };
+            if nomination.text.trim().to_lowercase() == normalized {
+                return Some((nomination_id, nomination));
+            }
+        }
+        None
+    }
+
+    /// Add every text in `texts` as a nomination by `user_id`, or none of them: the whole batch
+    /// is checked against the dedup strategy (including repeats within it) and the nomination
+    /// limit before anything is stored.
+    async fn nominate_batch(&mut self, user_id: &str, texts: Vec<String>) -> Result<(), MealVotingError> {
+        let strategy = *self.state.dedup_strategy.get();
+        let mut seen: Vec<String> = Vec::new();
+        let mut accepted = Vec::new();
+        let mut new_nominations = 0;
+        for text in texts {
+            let normalized = text.trim().to_lowercase();
+            if strategy != DedupStrategy::Allow {
+                if seen.contains(&normalized) {
+                    if strategy == DedupStrategy::Reject {
+                        return Err(MealVotingError::DuplicateNomination(text));
+                    }
+                    continue;
+                }
+                seen.push(normalized);
+                if let Some((nomination_id, _)) = self.find_duplicate(&text).await {
+                    if strategy == DedupStrategy::Reject {
+                        return Err(MealVotingError::DuplicateNomination(nomination_id));
+                    }
+                    // Merged into the existing nomination: no new one is stored.
+                    accepted.push(text);
+                    continue;
+                }
+            }
+            new_nominations += 1;
+            accepted.push(text);
+        }
+        self.ensure_nomination_allowance(user_id, new_nominations).await?;
+
+        for text in accepted {
+            if !self.accept_duplicate(user_id, &text).await? {
+                continue;
+            }
+            let nomination_id = self.allocate_nomination_id();
+            let nomination = Nomination { user_id: user_id.to_string(), text, base_score: 0, category: None, tags: Vec::new() };
+            self.state.nominations.insert(&nomination_id, nomination).expect("insert failed");
+        }
+        Ok(())
+    }
+
+    /// Reject ballots ranking nominations left off a published shortlist.
+    fn check_shortlisted(&self, rankings: &[String]) -> Result<(), MealVotingError> {
+        let shortlist = self.state.shortlist.get();
+        if shortlist.is_empty() {
+            return Ok(());
+        }
+        if let Some(nomination_id) = rankings.iter().find(|id| !shortlist.contains(id)) {
+            return Err(MealVotingError::NotShortlisted(nomination_id.clone()));
+        }
+        Ok(())
+    }
+
+    /// Reject ballots ranking more nominations of a category than its budget allows.
+    async fn check_category_budgets(&self, rankings: &[String]) -> Result<(), MealVotingError> {
+        let budgets = self.state.category_budgets.get();
+        if budgets.is_empty() {
+            return Ok(());
+        }
+        let mut categories = Vec::new();
+        for nomination_id in rankings {
+            if let Some(nomination) = self.state.nominations.get(nomination_id).await.expect("get failed") {
+                categories.extend(nomination.category);
+            }
+        }
+        for budget in budgets {
+            let picks = categories.iter().filter(|category| **category == budget.category).count();
+            if picks > budget.picks as usize {
+                return Err(MealVotingError::CategoryBudgetExceeded {
+                    category: budget.category.clone(),
+                    picks: budget.picks,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// This poll's settings, for a clone to start from. The schedule (`opens_at` and the
+    /// deadlines) is left unset, since those moments have usually passed by the time of a rerun.
+    async fn clone_config(&self) -> PollConfig {
+        PollConfig {
+            max_points_per_option: *self.state.max_points_per_option.get(),
+            ballot_limit_policy: *self.state.ballot_limit_policy.get(),
+            ballots_final: *self.state.ballots_final.get(),
+            opens_at: None,
+            fee_per_operation: *self.state.fee_per_operation.get(),
+            quorum_weight: *self.state.quorum_weight.get(),
+            allow_self_ranking: *self.state.allow_self_ranking.get(),
+            none_of_the_above: self.state.nominations.contains_key(NONE_OF_THE_ABOVE_ID).await.expect("contains failed"),
+            category_budgets: self.state.category_budgets.get().clone(),
+            decision_deadline: None,
+            dedup_strategy: *self.state.dedup_strategy.get(),
+            require_full_ranking: *self.state.require_full_ranking.get(),
+            winning_score_threshold: *self.state.winning_score_threshold.get(),
+            record_rejected_ballots: *self.state.record_rejected_ballots.get(),
+            tally_method: *self.state.tally_method.get(),
+            deadline_micros: None,
+            max_participants: *self.state.max_participants.get(),
+            quorum: *self.state.quorum.get(),
+            description: self.state.description.get().clone(),
+            category: self.state.category.get().clone(),
+            min_rankings: *self.state.min_rankings.get(),
+            max_nominations_per_user: *self.state.max_nominations_per_user.get(),
+            multi_winner: *self.state.multi_winner.get(),
+            hide_ballots_until_close: *self.state.hide_ballots_until_close.get(),
+            borda_variant: *self.state.borda_variant.get(),
+            tie_break_seed: *self.state.tie_break_seed.get(),
+            allow_write_ins: *self.state.allow_write_ins.get(),
+            anonymous_ballots: *self.state.anonymous_ballots.get(),
+        }
+    }
+
+    /// Spawn a new poll microchain, send it the initialization message and record it for `user_id`.
+    async fn open_poll_chain(
+        &mut self,
+        owner_id: AccountOwner,
+        user_id: String,
+        topic: String,
+        votes_per_voter: u32,
+        config: PollConfig,
+        nominations: Vec<(String, String)>,
+    ) {
+        let new_chain_id = self.runtime.open_chain(
+            ChainOwnership::single(owner_id),
+            ApplicationPermissions::default(),
+            Amount::from_tokens(10),
+        );
+
+        let msg = Message::InitializePoll {
+            topic,
+            votes_per_voter,
+            admin_id: user_id.clone(),
+            config: Box::new(config),
+            nominations,
+        };
+        self.runtime.prepare_message(msg).send_to(new_chain_id);
+
+        let mut polls = self.state.created_polls.get(&user_id).await.expect("get failed").unwrap_or_default();
+        polls.push(new_chain_id);
+        self.state.created_polls.insert(&user_id, polls).expect("insert failed");
+    }
+
+    /// Reject ballots ranking the voter's own nomination when self-ranking is disabled.
+    async fn check_not_self_ranked(&self, user_id: &str, rankings: &[String]) -> Result<(), MealVotingError> {
+        if *self.state.allow_self_ranking.get() {
+            return Ok(());
+        }
+        for nomination_id in rankings {
+            if let Some(nomination) = self.state.nominations.get(nomination_id).await.expect("get failed") {
+                if nomination.user_id == user_id {
+                    return Err(MealVotingError::SelfRanking);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the poll into voting, refusing when there is nothing on the ballot yet.
+    async fn start_vote(&mut self, user_id: &str) -> Result<(), MealVotingError> {
+        if user_id != self.state.admin_id.get() {
+            return Err(MealVotingError::NotAdmin("start voting"));
+        }
+        if self.state.nominations.count().await.expect("count failed") == 0 {
+            return Err(MealVotingError::EmptyPoll);
+        }
+        self.state.has_started.set(true);
+        self.emit_event(PollEvent::VotingStarted);
+        Ok(())
+    }
+
+    /// Reject closing while the combined weight of voters is below the configured quorum.
+    async fn ensure_quorum(&mut self) -> Result<(), MealVotingError> {
+        let Some(quorum_weight) = *self.state.quorum_weight.get() else {
+            return Ok(());
+        };
+        let mut voters = self.state.rankings.indices().await.expect("indices failed");
+        voters.extend(self.state.point_allocations.indices().await.expect("indices failed"));
+        voters.extend(self.state.star_ratings.indices().await.expect("indices failed"));
+        voters.sort();
+        voters.dedup();
+        let mut voted_weight = 0u64;
+        for voter in voters {
+            let weight = self.state.participant_weights.get(&voter).await.expect("get failed").unwrap_or(1);
+            voted_weight = voted_weight.saturating_add(weight);
+        }
+        if voted_weight < quorum_weight {
+            return Err(MealVotingError::QuorumNotMet { voted: voted_weight, required: quorum_weight });
+        }
+        Ok(())
+    }
+
+    /// Reject `adding` more nominations from `user_id` if they would then hold more than
+    /// `max_nominations_per_user`.
+    async fn ensure_nomination_allowance(&self, user_id: &str, adding: usize) -> Result<(), MealVotingError> {
+        let limit = *self.state.max_nominations_per_user.get();
+        if limit == 0 {
+            return Ok(());
+        }
+        let mut count = 0usize;
+        self.state
+            .nominations
+            .for_each_index_value(|_, nomination| {
+                if nomination.user_id == user_id {
+                    count += 1;
+                }
+                Ok(())
+            })
+            .await
+            .expect("iteration failed");
+        if count + adding > limit as usize {
+            return Err(MealVotingError::NominationLimitReached(limit));
+        }
+        Ok(())
+    }
+
+    /// Reject new participants once `max_participants` is reached. The admin and anyone already
+    /// in the poll (re-joining) are always let through.
+    async fn ensure_room_for(&self, user_id: &str) -> Result<(), MealVotingError> {
+        let Some(max_participants) = *self.state.max_participants.get() else {
+            return Ok(());
+        };
+        if user_id == self.state.admin_id.get()
+            || self.state.participants.contains_key(user_id).await.expect("contains failed")
+        {
+            return Ok(());
+        }
+        if self.state.participants.count().await.expect("count failed") >= max_participants as usize {
+            return Err(MealVotingError::PollFull(max_participants));
+        }
+        Ok(())
+    }
+
+    /// Reject participation before the poll's scheduled `opens_at` time.
+    fn ensure_open(&mut self) -> Result<(), MealVotingError> {
+        if let Some(opens_at) = *self.state.opens_at.get() {
+            if self.runtime.system_time() < opens_at {
+                return Err(MealVotingError::PollNotOpen);
+            }
+        }
+        Ok(())
+    }
+
+    /// Bring existing ballots in line with a new `votes_per_voter`, following the poll's
+    /// `BallotLimitPolicy`. Cumulative point ballots can't be rescaled, so a budget change is
+    /// refused once any exist.
+    async fn reconcile_ballots(&mut self, votes_per_voter: u32) -> Result<(), MealVotingError> {
+        if votes_per_voter != *self.state.votes_per_voter.get()
+            && self.state.point_allocations.count().await.expect("count failed") > 0
+        {
+            return Err(MealVotingError::PointBudgetLocked);
+        }
+        let limit = votes_per_voter as usize;
+        let policy = *self.state.ballot_limit_policy.get();
+        let indices = self.state.rankings.indices().await.expect("indices failed");
+        for user_id in indices {
+            let Some(mut rankings) = self.state.rankings.get(&user_id).await.expect("get failed") else {
+                continue;
+            };
+            if rankings.len() <= limit {
+                continue;
+            }
+            match policy {
+                BallotLimitPolicy::Reject => {
+                    return Err(MealVotingError::BallotsExceedLimit(votes_per_voter));
+                }
+                BallotLimitPolicy::Truncate => {
+                    rankings.truncate(limit);
+                    self.state.rankings.insert(&user_id, rankings).expect("insert failed");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The payout account registered for the top-ranked result.
+    async fn payout_recipient(&self) -> Result<Account, MealVotingError> {
+        let winner = self
+            .state
+            .results
+            .get()
+            .first()
+            .map(|entry| entry.nomination_id.clone())
+            .ok_or(MealVotingError::NoResults)?;
+        self.state
+            .payout_addresses
+            .get(&winner)
+            .await
+            .expect("get failed")
+            .ok_or(MealVotingError::NoPayoutAddress)
+    }
+
+    /// Count the incoming message against its origin chain's allowance for the current window.
+    /// Returns `false` once the origin chain has exceeded `MAX_MESSAGES_PER_WINDOW`.
+    async fn within_message_rate_limit(&mut self) -> bool {
+        let Some(origin) = self.runtime.message_origin_chain_id() else {
+            return true;
+        };
+        let now = self.runtime.system_time().micros();
+        if now.saturating_sub(*self.state.message_window_start.get()) >= MESSAGE_WINDOW_MICROS {
+            self.state.message_counts.clear();
+            self.state.message_window_start.set(now);
+        }
+        let count = self.state.message_counts.get(&origin).await.expect("get failed").unwrap_or(0) + 1;
+        self.state.message_counts.insert(&origin, count).expect("insert failed");
+        count <= MAX_MESSAGES_PER_WINDOW
+    }
+
+    /// Close the poll now and publish its results.
+    async fn close_poll(&mut self) {
+        self.state.is_closed.set(true);
+        let closed_at = self.runtime.system_time();
+        self.state.closed_at.set(Some(closed_at));
+        self.emit_event(PollEvent::PollClosed);
+        self.compute_results().await;
+        let ballots = self.state.rankings.count().await.expect("count failed");
+        self.state.quorum_met.set(ballots >= *self.state.quorum.get() as usize);
+        if let Some(creator_chain) = *self.state.creator_chain.get() {
+            let results = self.state.results.get().clone();
+            self.runtime.prepare_message(Message::ReportResults { results }).send_to(creator_chain);
+        }
+    }
+
+    /// Close the poll if its deadline has passed, returning whether it did. The late ballot that
+    /// triggers this is not counted; the operation still succeeds so that the closing sticks, and
+    /// later ballots fail on the closed poll.
+    async fn close_if_deadline_passed(&mut self) -> bool {
+        let Some(deadline) = *self.state.deadline_micros.get() else {
+            return false;
+        };
+        if *self.state.is_closed.get() || self.runtime.system_time().micros() < deadline {
+            return false;
+        }
+        self.close_poll().await;
+        true
+    }
+
+    /// In a sudden-death poll, close as soon as a provisional score reaches the threshold.
+    async fn close_if_threshold_reached(&mut self) {
+        let Some(threshold) = *self.state.winning_score_threshold.get() else {
+            return;
+        };
+        if self.state.tally_scores(None).await.values().any(|score| *score >= threshold) {
+            self.close_poll().await;
+        }
+    }
+
+    /// Compute and publish results using the poll's tally method. Results are ordered by score;
+    /// ties go to the lexically smaller nomination ID unless a tie-break seed was drawn (see
+    /// `sort_results`).
+    async fn compute_results(&mut self) {
+        let mut eliminations = BTreeMap::new();
+        let mut elected = BTreeSet::new();
+        let mut first_choices = BTreeMap::new();
+        for ballot in self.ranked_ballots().await {
+            if let Some(first_choice) = ballot.into_iter().next() {
+                *first_choices.entry(first_choice).or_insert(0u64) += 1;
+            }
+        }
+        let mut candidates = self.state.nominations.indices().await.expect("indices failed");
+        let shortlist = self.state.shortlist.get();
+        if !shortlist.is_empty() {
+            candidates.retain(|id| shortlist.contains(id));
+        }
+        let ballots = self.ranked_ballots().await;
+        self.state.condorcet_winner.set(condorcet_winner(&candidates, &ballots));
+        let scores = match *self.state.tally_method.get() {
+            TallyMethod::Borda => self.state.tally_scores(None).await,
+            TallyMethod::Plurality => first_choices.clone(),
+            TallyMethod::Approval => {
+                let mut approvals = BTreeMap::new();
+                for ballot in self.ranked_ballots().await {
+                    for nomination_id in ballot {
+                        *approvals.entry(nomination_id).or_insert(0u64) += 1;
+                    }
+                }
+                approvals
+            }
+            TallyMethod::InstantRunoff => {
+                let (scores, rounds) = instant_runoff(candidates, &ballots);
+                eliminations = rounds;
+                scores
+            }
+            TallyMethod::Stv => {
+                let seats = (*self.state.multi_winner.get()).max(1) as usize;
+                let count = single_transferable_vote(candidates, &ballots, seats);
+                eliminations = count.eliminations;
+                elected = count.elected;
+                count.scores
+            }
+        };
+        let mut results: Vec<ResultEntry> = Vec::new();
+        for (nomination_id, score) in scores {
+            let text = self
+                .state
+                .nominations
+                .get(&nomination_id)
+                .await
+                .expect("get failed")
+                .map(|n| n.text.clone())
+                .unwrap_or_else(|| "Unknown".to_string());
+            results.push(ResultEntry {
+                eliminated_in_round: eliminations.get(&nomination_id).copied(),
+                first_choice_votes: first_choices.get(&nomination_id).copied().unwrap_or(0),
+                elected: elected.contains(&nomination_id),
+                nomination_id,
+                nomination_text: text,
+                score,
+            });
+        }
+
+        let shortlist = self.state.shortlist.get();
+        if !shortlist.is_empty() {
+            results.retain(|entry| shortlist.contains(&entry.nomination_id));
+        }
+        self.add_partial_results(&mut results).await;
+
+        sort_results(&mut results, *self.state.tie_break_seed.get());
+        self.publish_results(results);
+        self.compute_category_results().await;
+    }
+
+    /// Every ranked ballot, in voter order.
+    async fn ranked_ballots(&self) -> Vec<Vec<String>> {
+        self.state.voter_ballots().await.into_iter().map(|(_, ballot)| ballot).collect()
+    }
+
+    /// Tally each budgeted category on its own: a ballot's picks within the category earn Borda
+    /// points from the category's allowance down, ignoring the rest of the ballot.
+    async fn compute_category_results(&mut self) {
+        let budgets = self.state.category_budgets.get().clone();
+        if budgets.is_empty() {
+            return;
+        }
+        let mut categories = BTreeMap::new();
+        let mut texts = BTreeMap::new();
+        let nomination_keys = self.state.nominations.indices().await.expect("indices failed");
+        for nomination_id in nomination_keys {
+            if let Some(nomination) = self.state.nominations.get(&nomination_id).await.expect("get failed") {
+                if let Some(category) = nomination.category {
+                    categories.insert(nomination_id.clone(), category);
+                }
+                texts.insert(nomination_id, nomination.text);
+            }
+        }
+        let ballots = self.ranked_ballots().await;
+        for budget in budgets {
+            let mut scores: BTreeMap<String, u64> = BTreeMap::new();
+            let mut first_choices: BTreeMap<String, u64> = BTreeMap::new();
+            for ballot in &ballots {
+                let picks = ballot.iter().filter(|id| categories.get(*id) == Some(&budget.category));
+                for (i, nomination_id) in picks.enumerate() {
+                    let points = (budget.picks as u64).saturating_sub(i as u64);
+                    let score = scores.entry(nomination_id.clone()).or_insert(0);
+                    *score = score.saturating_add(points);
+                    if i == 0 {
+                        *first_choices.entry(nomination_id.clone()).or_insert(0) += 1;
+                    }
+                }
+            }
+            let mut results: Vec<ResultEntry> = scores
+                .into_iter()
+                .map(|(nomination_id, score)| ResultEntry {
+                    nomination_text: texts.get(&nomination_id).cloned().unwrap_or_else(|| "Unknown".to_string()),
+                    first_choice_votes: first_choices.get(&nomination_id).copied().unwrap_or(0),
+                    nomination_id,
+                    score,
+                    eliminated_in_round: None,
+                    elected: false,
+                })
+                .collect();
+            sort_results(&mut results, *self.state.tie_break_seed.get());
+            self.state.category_results.insert(&budget.category, results).expect("insert failed");
+        }
+    }
+
+    /// Add every shard's latest partial results to the local tally, matched by nomination ID.
+    async fn add_partial_results(&self, results: &mut Vec<ResultEntry>) {
+        let shards = self.state.partial_results.indices().await.expect("indices failed");
+        for shard in shards {
+            let scores = self.state.partial_results.get(&shard).await.expect("get failed").unwrap_or_default();
+            for entry in scores {
+                let position = match results.iter().position(|total| total.nomination_id == entry.nomination_id) {
+                    Some(position) => position,
+                    None => {
+                        results.push(ResultEntry {
+                            nomination_id: entry.nomination_id.clone(),
+                            nomination_text: entry.nomination_text.clone(),
+                            score: 0,
+                            eliminated_in_round: None,
+                            first_choice_votes: 0,
+                            elected: false,
+                        });
+                        results.len() - 1
+                    }
+                };
+                let total = &mut results[position];
+                total.score = total.score.saturating_add(entry.score);
+                total.first_choice_votes = total.first_choice_votes.saturating_add(entry.first_choice_votes);
+            }
+        }
+    }
+
+    /// Store a sorted result set, record it in the history, flag a "None of the above" win and
+    /// emit it as a `ResultsComputed` event.
+    fn publish_results(&mut self, results: Vec<ResultEntry>) {
+        let nota_won = results.first().is_some_and(|winner| winner.nomination_id == NONE_OF_THE_ABOVE_ID);
+        self.state.no_acceptable_option.set(nota_won);
+        self.emit_event(PollEvent::ResultsComputed { results: results.clone() });
+        self.state.results_history.push(results.clone());
+        self.state.results.set(results);
+    }
+
+    /// Publish an event on the poll's `POLL_EVENTS_STREAM` for off-chain indexers.
+    fn emit_event(&mut self, event: PollEvent) {
+        self.runtime.emit(StreamName(POLL_EVENTS_STREAM.to_vec()), &event);
+    }
+
+    /// Derive the poll's ballot salt from its chain, creator, topic and creation time. These are
+    /// all public, so the salt only keeps one voter's labels from matching across polls.
+    fn draw_ballot_salt(&mut self, admin_id: &str, topic: &str) -> String {
+        let chain_id = self.runtime.chain_id();
+        let origin = self.runtime.message_origin_chain_id();
+        let created_at = self.runtime.system_time();
+        let preimage = serde_json::to_vec(&(chain_id, origin, created_at, admin_id, topic)).expect("salt inputs serialize");
+        Sha256::digest(&preimage).iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// Derive a fresh tie-break seed from the previous seed, the block time and the number of
+    /// result sets published so far, so repeated re-draws within a block still differ.
+    fn draw_tie_break_seed(&mut self) -> u64 {
+        let previous = self.state.tie_break_seed.get().unwrap_or(0);
+        let entropy = format!("{}:{}", self.runtime.system_time().micros(), self.state.results_history.count());
+        tie_break_hash(previous, &entropy)
+    }
+}
+
+/// Lowercase and trim tags, dropping empty and repeated ones.
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+/// Sort results by descending score. Ties are ordered by `tie_break_hash(seed, nomination_id)`
+/// when a seed is given, and otherwise (or if the hashes collide) by `nomination_id`, compared
+/// lexically, so every chain produces the same order.
+fn sort_results(results: &mut [ResultEntry], seed: Option<u64>) {
+    // STV seats come first, then instant-runoff survivors, then nominations eliminated later
+    // before earlier ones.
+    let survival = |entry: &ResultEntry| entry.eliminated_in_round.unwrap_or(u32::MAX);
+    results.sort_by(|a, b| {
+        b.elected
+            .cmp(&a.elected)
+            .then_with(|| survival(b).cmp(&survival(a)))
+            .then_with(|| b.score.cmp(&a.score))
+            .then_with(|| match seed {
+                Some(seed) => tie_break_hash(seed, &a.nomination_id).cmp(&tie_break_hash(seed, &b.nomination_id)),
+                None => std::cmp::Ordering::Equal,
+            })
+            .then_with(|| a.nomination_id.cmp(&b.nomination_id))
+    });
+}
+
+/// Run an instant-runoff count over ranked ballots. Each round, every ballot counts for its
+/// highest-ranked remaining candidate; if none holds a majority of those votes, the candidate with
+/// the fewest is eliminated (ties eliminate the last in ID order) and the next round begins.
+/// Returns each candidate's votes in its last round and the round (from 1) it was eliminated in.
+fn instant_runoff(
+    mut candidates: Vec<String>,
+    ballots: &[Vec<String>],
+) -> (BTreeMap<String, u64>, BTreeMap<String, u32>) {
+    let mut scores = BTreeMap::new();
+    let mut eliminations = BTreeMap::new();
+    let mut round = 1;
+    loop {
+        let mut counts: BTreeMap<String, u64> = candidates.iter().map(|id| (id.clone(), 0)).collect();
+        for ballot in ballots {
+            if let Some(choice) = ballot.iter().find(|id| counts.contains_key(*id)) {
+                *counts.get_mut(choice).expect("choice is a candidate") += 1;
+            }
+        }
+        let total: u64 = counts.values().sum();
+        scores.extend(counts.iter().map(|(id, votes)| (id.clone(), *votes)));
+        let leader = counts.values().copied().max().unwrap_or(0);
+        if candidates.len() <= 1 || total == 0 || leader * 2 > total {
+            return (scores, eliminations);
+        }
+        let (loser, _) = counts
+            .iter()
+            .rev()
+            .min_by_key(|(_, votes)| **votes)
+            .expect("at least two candidates remain");
+        let loser = loser.clone();
+        candidates.retain(|id| *id != loser);
+        eliminations.insert(loser, round);
+        round += 1;
+    }
+}
+
+/// Fixed-point unit of one ballot's value in an STV count, so surplus transfers can pass on a
+/// fraction of a vote.
+const STV_VOTE_SCALE: u64 = 1_000_000;
+
+/// Outcome of `single_transferable_vote`.
+struct StvCount {
+    /// Whole votes each candidate held when it was elected or eliminated, or at the end.
+    scores: BTreeMap<String, u64>,
+    /// Round (from 1) each eliminated candidate went out in.
+    eliminations: BTreeMap<String, u32>,
+    elected: BTreeSet<String>,
+}
+
+/// Run a single transferable vote count filling `seats` from ranked ballots. Each round, every
+/// ballot counts at its current value for its highest-ranked hopeful candidate. The leader is
+/// elected once it reaches the Droop quota, and each ballot it holds keeps only the surplus share
+/// of its value for the next preference (Gregory method). If nobody reaches the quota, the
+/// candidate with the fewest votes is eliminated (ties eliminate the last in ID order). Once the
+/// hopefuls left just fill the remaining seats, they are all elected.
+fn single_transferable_vote(candidates: Vec<String>, ballots: &[Vec<String>], seats: usize) -> StvCount {
+    // Abstentions are empty ballots: they back nobody, so they don't raise the quota either.
+    let cast = ballots.iter().filter(|ballot| !ballot.is_empty()).count() as u64;
+    let quota = (cast / (seats as u64 + 1) + 1) * STV_VOTE_SCALE;
+    let mut values = vec![STV_VOTE_SCALE; ballots.len()];
+    let mut hopeful = candidates;
+    let mut count = StvCount { scores: BTreeMap::new(), eliminations: BTreeMap::new(), elected: BTreeSet::new() };
+    let mut round = 1;
+    while count.elected.len() < seats && !hopeful.is_empty() {
+        let mut tallies: BTreeMap<String, u64> = hopeful.iter().map(|id| (id.clone(), 0)).collect();
+        let mut holders: Vec<Option<&String>> = Vec::with_capacity(ballots.len());
+        for (ballot, value) in ballots.iter().zip(&values) {
+            let choice = ballot.iter().find(|id| tallies.contains_key(*id));
+            if let Some(choice) = choice {
+                *tallies.get_mut(choice).expect("choice is hopeful") += value;
+            }
+            holders.push(choice);
+        }
+        count.scores.extend(tallies.iter().map(|(id, tally)| (id.clone(), tally / STV_VOTE_SCALE)));
+        if count.elected.len() + hopeful.len() <= seats {
+            count.elected.extend(hopeful.drain(..));
+            break;
+        }
+        let (leader, leader_tally) = tallies
+            .iter()
+            .rev()
+            .max_by_key(|(_, tally)| **tally)
+            .map(|(id, tally)| (id.clone(), *tally))
+            .expect("hopefuls remain");
+        if leader_tally >= quota {
+            let surplus = leader_tally - quota;
+            for (value, holder) in values.iter_mut().zip(&holders) {
+                if *holder == Some(&leader) {
+                    *value = *value * surplus / leader_tally;
+                }
+            }
+            hopeful.retain(|id| *id != leader);
+            count.elected.insert(leader);
+        } else {
+            let (loser, _) = tallies
+                .iter()
+                .rev()
+                .min_by_key(|(_, tally)| **tally)
+                .expect("hopefuls remain");
+            let loser = loser.clone();
+            hopeful.retain(|id| *id != loser);
+            count.eliminations.insert(loser, round);
+        }
+        round += 1;
+    }
+    count
+}
+
+/// The candidate preferred over every other candidate by a strict majority of the ranked ballots
+/// comparing them. A ranked option beats an unranked one; two unranked options are not compared.
+/// `None` when there are no ballots or the pairwise preferences form a cycle or tie.
+fn condorcet_winner(candidates: &[String], ballots: &[Vec<String>]) -> Option<String> {
+    if ballots.is_empty() {
+        return None;
+    }
+    let prefers = |ballot: &Vec<String>, a: &String, b: &String| {
+        match (ballot.iter().position(|id| id == a), ballot.iter().position(|id| id == b)) {
+            (Some(x), Some(y)) => x < y,
+            (Some(_), None) => true,
+            _ => false,
+        }
+    };
+    candidates
+        .iter()
+        .find(|a| {
+            candidates.iter().filter(|b| b != a).all(|b| {
+                let for_a = ballots.iter().filter(|ballot| prefers(ballot, a, b)).count();
+                let for_b = ballots.iter().filter(|ballot| prefers(ballot, b, a)).count();
+                for_a > for_b
+            })
+        })
+        .cloned()
+}
+
+/// Reject settings that contradict each other or `votes_per_voter`, so a poll can't be created
+/// that no ballot could satisfy.
+fn validate_config(votes_per_voter: u32, config: &PollConfig) -> Result<(), MealVotingError> {
+    if config.min_rankings > votes_per_voter {
+        return Err(MealVotingError::InvalidConfiguration("min rankings must not exceed votes per voter"));
+    }
+    if config.max_points_per_option == Some(0) {
+        return Err(MealVotingError::InvalidConfiguration("max points per option must be at least 1"));
+    }
+    if config.max_participants == Some(0) {
+        return Err(MealVotingError::InvalidConfiguration("max participants must leave room for the admin"));
+    }
+    if config.category_budgets.iter().any(|budget| budget.category.trim().is_empty()) {
+        return Err(MealVotingError::InvalidConfiguration("category budgets must name a category"));
+    }
+    // A write-in is nominated by its voter, so it would count as ranking their own nomination.
+    if config.allow_write_ins && !config.allow_self_ranking {
+        return Err(MealVotingError::InvalidConfiguration("write-ins need self-ranking to be allowed"));
+    }
+    if let (Some(opens_at), Some(deadline)) = (config.opens_at, config.deadline_micros) {
+        if deadline <= opens_at.micros() {
+            return Err(MealVotingError::InvalidConfiguration("deadline must be after the opening time"));
+        }
+    }
+    Ok(())
+}
+
+/// 64-bit FNV-1a over the little-endian seed followed by the string's bytes.
+fn tie_break_hash(seed: u64, value: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in seed.to_le_bytes().iter().chain(value.as_bytes()) {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use futures::FutureExt as _;
+    use linera_sdk::{
+        linera_base_types::{Account, AccountOwner, ChainId, CryptoHash, Timestamp},
+        util::BlockingWait,
+        views::View,
+        Contract, ContractRuntime,
+    };
+    use meal_voting::{
+        BallotLimitPolicy, BordaVariant, CategoryBudget, DedupStrategy, Message, Operation, PollConfig, PollEvent,
+        TallyMethod, POLL_EVENTS_STREAM,
+    };
+
+    use super::{
+        single_transferable_vote, sort_results, tie_break_hash, MealVotingContract, MealVotingError, PollState,
+        ResultEntry, MAX_MESSAGES_PER_WINDOW,
+    };
+
+    fn create_poll_with_config(votes_per_voter: u32, config: PollConfig) -> MealVotingContract {
+        let runtime = ContractRuntime::new().with_application_parameters(());
+        create_poll_on(runtime, votes_per_voter, config)
+    }
+
+    fn create_poll_on(
+        runtime: ContractRuntime<MealVotingContract>,
+        votes_per_voter: u32,
+        config: PollConfig,
+    ) -> MealVotingContract {
+        let mut contract = MealVotingContract {
+            state: PollState::load(runtime.root_view_storage_context())
+                .blocking_wait()
+                .expect("Failed to read from mock key value store"),
+            runtime,
+        };
+
+        contract
+            .execute_message(Message::InitializePoll {
+                topic: "Lunch".to_string(),
+                votes_per_voter,
+                admin_id: "admin".to_string(),
+                config: Box::new(config),
+                nominations: Vec::new(),
+            })
+            .now_or_never()
+            .expect("Initialization should not await anything");
+
+        contract
+    }
+
+    fn execute(contract: &mut MealVotingContract, operation: Operation) {
+        contract
+            .execute_operation(operation)
+            .now_or_never()
+            .expect("Execution of operation should not await anything");
+    }
+
+    fn try_execute(contract: &mut MealVotingContract, operation: Operation) -> Result<(), MealVotingError> {
+        contract
+            .try_execute_operation(operation)
+            .now_or_never()
+            .expect("Execution of operation should not await anything")
+    }
+
+    #[test]
+    fn refused_operations_return_their_error_variant() {
+        let owner = |name: &str| name.to_string();
+        let mut contract = create_poll_with_config(2, PollConfig::default());
+        execute(&mut contract, Operation::Join { name: "alice".to_string(), owner: owner("alice") });
+        assert_eq!(
+            try_execute(&mut contract, Operation::Nominate { text: "Pizza".to_string(), category: None, tags: Vec::new(), owner: owner("carol") }),
+            Err(MealVotingError::NotAParticipant)
+        );
+        execute(&mut contract, Operation::Nominate { text: "Pizza".to_string(), category: None, tags: Vec::new(), owner: owner("alice") });
+        assert_eq!(
+            try_execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: owner("alice") }),
+            Err(MealVotingError::VotingNotStarted)
+        );
+        assert_eq!(
+            try_execute(&mut contract, Operation::StartVote { owner: owner("alice") }),
+            Err(MealVotingError::NotAdmin("start voting"))
+        );
+        assert_eq!(
+            try_execute(&mut contract, Operation::EndorseNomination { nomination_id: "nom_9".to_string(), owner: owner("alice") }),
+            Err(MealVotingError::UnknownNomination("nom_9".to_string()))
+        );
+        assert_eq!(
+            try_execute(&mut contract, Operation::CommentOnNomination { nomination_id: "nom_0".to_string(), text: " ".to_string(), owner: owner("alice") }),
+            Err(MealVotingError::InvalidComment)
+        );
+        assert_eq!(
+            try_execute(&mut contract, Operation::SetVotesPerVoter { votes_per_voter: 0, owner: owner("admin") }),
+            Err(MealVotingError::ZeroVotesPerVoter)
+        );
+        assert_eq!(
+            try_execute(&mut contract, Operation::ResumePoll { owner: owner("admin") }),
+            Err(MealVotingError::PollNotPaused)
+        );
+        assert_eq!(
+            try_execute(&mut contract, Operation::PayoutWinner { owner: owner("admin") }),
+            Err(MealVotingError::PollNotClosed)
+        );
+
+        execute(&mut contract, Operation::StartVote { owner: owner("admin") });
+        assert_eq!(
+            try_execute(&mut contract, Operation::Join { name: "carol".to_string(), owner: owner("carol") }),
+            Err(MealVotingError::JoiningClosed)
+        );
+        assert_eq!(
+            try_execute(&mut contract, Operation::Nominate { text: "Sushi".to_string(), category: None, tags: Vec::new(), owner: owner("alice") }),
+            Err(MealVotingError::NominatingClosed)
+        );
+        let rankings = vec!["nom_0".to_string(), "nom_0".to_string(), "nom_0".to_string()];
+        assert_eq!(
+            try_execute(&mut contract, Operation::Vote { rankings, owner: owner("alice") }),
+            Err(MealVotingError::TooManyRankings(2))
+        );
+        assert_eq!(
+            try_execute(&mut contract, Operation::VotePoints { allocations: vec![("nom_0".to_string(), 1)], owner: owner("alice") }),
+            Err(MealVotingError::WrongPointTotal { budget: 2, total: 1 })
+        );
+        assert_eq!(
+            try_execute(&mut contract, Operation::RateOptions { ratings: vec![("nom_0".to_string(), 6)], owner: owner("alice") }),
+            Err(MealVotingError::InvalidRating(6))
+        );
+
+        execute(&mut contract, Operation::PausePoll { owner: owner("admin") });
+        assert_eq!(
+            try_execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: owner("alice") }),
+            Err(MealVotingError::PollPaused)
+        );
+        execute(&mut contract, Operation::ResumePoll { owner: owner("admin") });
+
+        execute(&mut contract, Operation::ClosePoll { owner: owner("admin") });
+        assert_eq!(
+            try_execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: owner("alice") }),
+            Err(MealVotingError::PollClosed)
+        );
+        assert_eq!(
+            try_execute(&mut contract, Operation::PayoutWinner { owner: owner("admin") }),
+            Err(MealVotingError::NoResults)
+        );
+        execute(&mut contract, Operation::FinalizeResults { owner: owner("admin") });
+        assert_eq!(
+            try_execute(&mut contract, Operation::ReopenPoll { owner: owner("admin") }),
+            Err(MealVotingError::ResultsFinalized)
+        );
+    }
+
+    #[test]
+    fn refused_ballots_and_settings_return_their_error_variant() {
+        let owner = |name: &str| name.to_string();
+        let runtime = ContractRuntime::new().with_application_parameters(()).with_system_time(Timestamp::from(0));
+        let config = PollConfig { opens_at: Some(Timestamp::from(100)), ..PollConfig::default() };
+        let mut contract = create_poll_on(runtime, 2, config);
+        assert_eq!(
+            try_execute(&mut contract, Operation::Join { name: "alice".to_string(), owner: owner("alice") }),
+            Err(MealVotingError::PollNotOpen)
+        );
+
+        let config = PollConfig {
+            dedup_strategy: DedupStrategy::Reject,
+            allow_self_ranking: false,
+            category_budgets: vec![CategoryBudget { category: "main".to_string(), picks: 1 }],
+            max_points_per_option: Some(1),
+            ballots_final: true,
+            quorum_weight: Some(5),
+            ..PollConfig::default()
+        };
+        let mut contract = create_poll_with_config(2, config);
+        for name in ["alice", "bob", "carol"] {
+            execute(&mut contract, Operation::Join { name: name.to_string(), owner: owner(name) });
+        }
+        let main = || Some("main".to_string());
+        execute(&mut contract, Operation::Nominate { text: "Pizza".to_string(), category: main(), tags: Vec::new(), owner: owner("alice") });
+        execute(&mut contract, Operation::Nominate { text: "Sushi".to_string(), category: main(), tags: Vec::new(), owner: owner("bob") });
+        execute(&mut contract, Operation::Nominate { text: "Curry".to_string(), category: main(), tags: Vec::new(), owner: owner("carol") });
+        execute(&mut contract, Operation::Nominate { text: "Tacos".to_string(), category: None, tags: Vec::new(), owner: owner("bob") });
+        assert_eq!(
+            try_execute(&mut contract, Operation::Nominate { text: " pizza".to_string(), category: None, tags: Vec::new(), owner: owner("carol") }),
+            Err(MealVotingError::DuplicateNomination("nom_0".to_string()))
+        );
+        let account = Account::new(ChainId(CryptoHash::test_hash("payout chain")), AccountOwner::from(CryptoHash::test_hash("bob")));
+        assert_eq!(
+            try_execute(&mut contract, Operation::SetPayoutAddress { nomination_id: "nom_0".to_string(), address: account, owner: owner("bob") }),
+            Err(MealVotingError::NotNominator("set the payout address"))
+        );
+        execute(&mut contract, Operation::EndorseNomination { nomination_id: "nom_1".to_string(), owner: owner("alice") });
+        assert_eq!(
+            try_execute(&mut contract, Operation::EndorseNomination { nomination_id: "nom_1".to_string(), owner: owner("alice") }),
+            Err(MealVotingError::AlreadyEndorsed)
+        );
+
+        execute(&mut contract, Operation::StartVote { owner: owner("admin") });
+        assert_eq!(
+            try_execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: owner("alice") }),
+            Err(MealVotingError::SelfRanking)
+        );
+        assert_eq!(
+            try_execute(&mut contract, Operation::Vote { rankings: vec!["nom_1".to_string(), "nom_2".to_string()], owner: owner("alice") }),
+            Err(MealVotingError::CategoryBudgetExceeded { category: "main".to_string(), picks: 1 })
+        );
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_1".to_string(), "nom_3".to_string()], owner: owner("alice") });
+        assert_eq!(
+            try_execute(&mut contract, Operation::Vote { rankings: vec!["nom_1".to_string()], owner: owner("alice") }),
+            Err(MealVotingError::BallotsFinal)
+        );
+        assert_eq!(
+            try_execute(&mut contract, Operation::SetVotesPerVoter { votes_per_voter: 1, owner: owner("admin") }),
+            Err(MealVotingError::BallotsExceedLimit(1))
+        );
+        execute(&mut contract, Operation::SetShortlist { nomination_ids: vec!["nom_1".to_string()], owner: owner("admin") });
+        assert_eq!(
+            try_execute(&mut contract, Operation::Vote { rankings: vec!["nom_3".to_string()], owner: owner("carol") }),
+            Err(MealVotingError::NotShortlisted("nom_3".to_string()))
+        );
+        assert_eq!(
+            try_execute(&mut contract, Operation::VotePoints { allocations: vec![("nom_0".to_string(), 2)], owner: owner("carol") }),
+            Err(MealVotingError::TooManyPoints(1))
+        );
+        let allocations = vec![("nom_0".to_string(), 1), ("nom_1".to_string(), 1)];
+        execute(&mut contract, Operation::VotePoints { allocations, owner: owner("carol") });
+        assert_eq!(
+            try_execute(&mut contract, Operation::SetVotesPerVoter { votes_per_voter: 3, owner: owner("admin") }),
+            Err(MealVotingError::PointBudgetLocked)
+        );
+        assert_eq!(
+            try_execute(&mut contract, Operation::ClosePoll { owner: owner("admin") }),
+            Err(MealVotingError::QuorumNotMet { voted: 2, required: 5 })
+        );
+
+        let mut contract = voting_poll_with_config(2, PollConfig { require_full_ranking: true, ..PollConfig::default() });
+        assert_eq!(
+            try_execute(&mut contract, Operation::Vote { rankings: vec!["nom_1".to_string()], owner: owner("alice") }),
+            Err(MealVotingError::IncompleteRanking { required: 2, ranked: 1 })
+        );
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_1".to_string(), "nom_0".to_string()], owner: owner("alice") });
+        execute(&mut contract, Operation::ClosePoll { owner: owner("admin") });
+        assert_eq!(try_execute(&mut contract, Operation::RerollTieBreak { owner: owner("admin") }), Err(MealVotingError::NoTie));
+        assert_eq!(
+            try_execute(&mut contract, Operation::PayoutWinner { owner: owner("admin") }),
+            Err(MealVotingError::NoPayoutAddress)
+        );
+    }
+
+    /// Creates a poll with two participants, two nominations (`nom_0`, `nom_1`) and voting started.
+    fn voting_poll(votes_per_voter: u32) -> MealVotingContract {
+        voting_poll_with_config(votes_per_voter, PollConfig::default())
+    }
+
+    fn voting_poll_with_config(votes_per_voter: u32, config: PollConfig) -> MealVotingContract {
+        let mut contract = create_poll_with_config(votes_per_voter, config);
+        for name in ["alice", "bob"] {
+            execute(&mut contract, Operation::Join { name: name.to_string(), owner: name.to_string() });
+        }
+        execute(&mut contract, Operation::Nominate { text: "Pizza".to_string(), category: None, tags: Vec::new(), owner: "alice".to_string() });
+        execute(&mut contract, Operation::Nominate { text: "Sushi".to_string(), category: None, tags: Vec::new(), owner: "bob".to_string() });
+        execute(&mut contract, Operation::StartVote { owner: "admin".to_string() });
+        contract
+    }
+
+    #[test]
+    #[should_panic(expected = "Allocations must total exactly 10 points")]
+    fn over_budget_point_allocations_are_rejected() {
+        let mut contract = voting_poll(10);
+        execute(
+            &mut contract,
+            Operation::VotePoints {
+                allocations: vec![("nom_0".to_string(), 8), ("nom_1".to_string(), 5)],
+                owner: "alice".to_string(),
+            },
+        );
+    }
+
+    #[test]
+    fn point_allocations_are_summed_per_nomination() {
+        let mut contract = voting_poll(10);
+        execute(
+            &mut contract,
+            Operation::VotePoints {
+                allocations: vec![("nom_0".to_string(), 7), ("nom_1".to_string(), 3)],
+                owner: "alice".to_string(),
+            },
+        );
+        execute(
+            &mut contract,
+            Operation::VotePoints {
+                allocations: vec![("nom_0".to_string(), 2), ("nom_1".to_string(), 8)],
+                owner: "bob".to_string(),
+            },
+        );
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+
+        let results = contract.state.results.get();
+        assert_eq!(results[0].nomination_text, "Sushi");
+        assert_eq!(results[0].score, 11);
+        assert_eq!(results[1].nomination_text, "Pizza");
+        assert_eq!(results[1].score, 9);
+    }
+
+    #[test]
+    #[should_panic(expected = "At most 6 points may go to a single nomination")]
+    fn point_allocation_above_per_option_cap_is_rejected() {
+        let config = PollConfig { max_points_per_option: Some(6), ..PollConfig::default() };
+        let mut contract = voting_poll_with_config(10, config);
+        execute(
+            &mut contract,
+            Operation::VotePoints {
+                allocations: vec![("nom_0".to_string(), 7), ("nom_1".to_string(), 3)],
+                owner: "alice".to_string(),
+            },
+        );
+    }
+
+    #[test]
+    fn splitting_points_for_one_option_across_entries_is_rejected() {
+        let config = PollConfig { max_points_per_option: Some(6), ..PollConfig::default() };
+        let mut contract = voting_poll_with_config(10, config);
+        let allocations = vec![("nom_0".to_string(), 6), ("nom_0".to_string(), 4)];
+        assert_eq!(
+            try_execute(&mut contract, Operation::VotePoints { allocations, owner: "alice".to_string() }),
+            Err(MealVotingError::DuplicateRanking("nom_0".to_string()))
+        );
+        assert!(!contract.state.point_allocations.contains_key("alice").now_or_never().unwrap().unwrap());
+    }
+
+    #[test]
+    fn messages_beyond_rate_limit_from_one_chain_are_dropped() {
+        let spammer = ChainId(CryptoHash::test_hash("spammer"));
+        let runtime = ContractRuntime::new()
+            .with_application_parameters(())
+            .with_message_origin_chain_id(spammer);
+        let mut contract = create_poll_on(runtime, 3, PollConfig::default());
+
+        for i in 0..MAX_MESSAGES_PER_WINDOW + 5 {
+            contract
+                .execute_message(Message::Nominate { user_id: "mallory".to_string(), text: format!("Spam {}", i), category: None, tags: Vec::new() })
+                .now_or_never()
+                .expect("Message handling should not await anything");
+        }
+
+        let nominations = contract.state.nominations.count().now_or_never().unwrap().unwrap();
+        assert_eq!(nominations, MAX_MESSAGES_PER_WINDOW as usize);
+        assert_eq!(*contract.state.dropped_messages.get(), 5);
+    }
+
+    #[test]
+    fn internal_messages_are_exempt_from_the_rate_limit() {
+        let poll_chain = ChainId(CryptoHash::test_hash("busy poll"));
+        let runtime = ContractRuntime::new()
+            .with_application_parameters(())
+            .with_message_origin_chain_id(poll_chain);
+        let mut factory = MealVotingContract {
+            state: PollState::load(runtime.root_view_storage_context()).blocking_wait().unwrap(),
+            runtime,
+        };
+
+        for i in 0..MAX_MESSAGES_PER_WINDOW + 5 {
+            factory
+                .execute_message(Message::JoinedPoll { user_id: format!("user {}", i) })
+                .now_or_never()
+                .expect("Message handling should not await anything");
+        }
+        factory.execute_message(Message::ReportResults { results: Vec::new() }).now_or_never().unwrap();
+
+        assert_eq!(factory.state.joined_polls.count().now_or_never().unwrap().unwrap(), MAX_MESSAGES_PER_WINDOW as usize + 5);
+        assert!(factory.state.poll_results.contains_key(&poll_chain).now_or_never().unwrap().unwrap());
+    }
+
+    #[test]
+    fn payout_goes_to_winning_nominators_address() {
+        let mut contract = voting_poll(2);
+        let chain_id = ChainId(CryptoHash::test_hash("payout chain"));
+        let alice_account = Account::new(chain_id, AccountOwner::from(CryptoHash::test_hash("alice")));
+        let bob_account = Account::new(chain_id, AccountOwner::from(CryptoHash::test_hash("bob")));
+        execute(
+            &mut contract,
+            Operation::SetPayoutAddress { nomination_id: "nom_0".to_string(), address: alice_account, owner: "alice".to_string() },
+        );
+        execute(
+            &mut contract,
+            Operation::SetPayoutAddress { nomination_id: "nom_1".to_string(), address: bob_account, owner: "bob".to_string() },
+        );
+        for voter in ["alice", "bob"] {
+            execute(
+                &mut contract,
+                Operation::Vote { rankings: vec!["nom_1".to_string(), "nom_0".to_string()], owner: voter.to_string() },
+            );
+        }
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+
+        let recipient = contract.payout_recipient().now_or_never().unwrap();
+        assert_eq!(recipient, Ok(bob_account));
+    }
+
+    fn cast_long_ballot(contract: &mut MealVotingContract) {
+        execute(
+            contract,
+            Operation::Vote { rankings: vec!["nom_1".to_string(), "nom_0".to_string()], owner: "alice".to_string() },
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Existing ballots exceed the new limit of 1")]
+    fn lowering_votes_per_voter_is_rejected_by_default() {
+        let mut contract = voting_poll(2);
+        cast_long_ballot(&mut contract);
+        execute(&mut contract, Operation::SetVotesPerVoter { votes_per_voter: 1, owner: "admin".to_string() });
+    }
+
+    #[test]
+    fn lowering_votes_per_voter_truncates_ballots_when_configured() {
+        let config = PollConfig { ballot_limit_policy: BallotLimitPolicy::Truncate, ..PollConfig::default() };
+        let mut contract = voting_poll_with_config(2, config);
+        cast_long_ballot(&mut contract);
+        execute(&mut contract, Operation::SetVotesPerVoter { votes_per_voter: 1, owner: "admin".to_string() });
+
+        let ballot = contract.state.rankings.get("alice").now_or_never().unwrap().unwrap();
+        assert_eq!(ballot, Some(vec!["nom_1".to_string()]));
+        assert_eq!(*contract.state.votes_per_voter.get(), 1);
+    }
+
+    #[test]
+    fn second_vote_overwrites_by_default() {
+        let mut contract = voting_poll(2);
+        cast_long_ballot(&mut contract);
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+
+        let ballot = contract.state.rankings.get("alice").now_or_never().unwrap().unwrap();
+        assert_eq!(ballot, Some(vec!["nom_0".to_string()]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Ballot already cast and ballots are final")]
+    fn second_vote_is_rejected_when_ballots_are_final() {
+        let config = PollConfig { ballots_final: true, ..PollConfig::default() };
+        let mut contract = voting_poll_with_config(2, config);
+        cast_long_ballot(&mut contract);
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+    }
+
+    #[test]
+    fn endorsements_accumulate_per_nomination() {
+        let mut contract = voting_poll(2);
+        for endorser in ["alice", "bob"] {
+            execute(
+                &mut contract,
+                Operation::EndorseNomination { nomination_id: "nom_1".to_string(), owner: endorser.to_string() },
+            );
+        }
+
+        let endorsers = contract.state.endorsements.get("nom_1").now_or_never().unwrap().unwrap();
+        assert_eq!(endorsers, Some(vec!["alice".to_string(), "bob".to_string()]));
+        let untouched = contract.state.endorsements.get("nom_0").now_or_never().unwrap().unwrap();
+        assert_eq!(untouched, None);
+    }
+
+    #[test]
+    fn rerolling_tie_break_can_change_winner_and_records_history() {
+        let mut contract = voting_poll(1);
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_1".to_string()], owner: "bob".to_string() });
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+        let original_winner = contract.state.results.get()[0].nomination_id.clone();
+
+        let mut rerolls = 0;
+        while contract.state.results.get()[0].nomination_id == original_winner {
+            assert!(rerolls < 16, "re-draws never changed the tie winner");
+            execute(&mut contract, Operation::RerollTieBreak { owner: "admin".to_string() });
+            rerolls += 1;
+        }
+
+        assert_eq!(contract.state.results_history.count(), rerolls + 1);
+        let original = contract.state.results_history.get(0).now_or_never().unwrap().unwrap().unwrap();
+        assert_eq!(original[0].nomination_id, original_winner);
+    }
+
+    #[test]
+    fn comments_attach_to_their_nomination_in_order() {
+        let mut contract = voting_poll(2);
+        for (nomination_id, text, owner) in [
+            ("nom_0", "Great crust", "bob"),
+            ("nom_1", "Too pricey", "alice"),
+            ("nom_0", "  Agreed  ", "alice"),
+        ] {
+            execute(
+                &mut contract,
+                Operation::CommentOnNomination {
+                    nomination_id: nomination_id.to_string(),
+                    text: text.to_string(),
+                    owner: owner.to_string(),
+                },
+            );
+        }
+
+        let comments = contract.state.comments.get("nom_0").now_or_never().unwrap().unwrap().unwrap();
+        let texts: Vec<_> = comments.iter().map(|comment| (comment.user_id.as_str(), comment.text.as_str())).collect();
+        assert_eq!(texts, vec![("bob", "Great crust"), ("alice", "Agreed")]);
+        let other = contract.state.comments.get("nom_1").now_or_never().unwrap().unwrap().unwrap();
+        assert_eq!(other.len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Comments must be between 1 and 280 characters")]
+    fn overlong_comments_are_rejected() {
+        let mut contract = voting_poll(2);
+        execute(
+            &mut contract,
+            Operation::CommentOnNomination { nomination_id: "nom_0".to_string(), text: "a".repeat(281), owner: "bob".to_string() },
+        );
+    }
+
+    fn scheduled_poll_at(now: u64) -> MealVotingContract {
+        let runtime = ContractRuntime::new()
+            .with_application_parameters(())
+            .with_system_time(Timestamp::from(now));
+        let config = PollConfig { opens_at: Some(Timestamp::from(1_000)), ..PollConfig::default() };
+        create_poll_on(runtime, 2, config)
+    }
+
+    #[test]
+    #[should_panic(expected = "Poll not yet open")]
+    fn joining_before_opens_at_is_rejected() {
+        let mut contract = scheduled_poll_at(999);
+        execute(&mut contract, Operation::Join { name: "alice".to_string(), owner: "alice".to_string() });
+    }
+
+    #[test]
+    fn joining_and_nominating_after_opens_at_is_allowed() {
+        let mut contract = scheduled_poll_at(1_000);
+        execute(&mut contract, Operation::Join { name: "alice".to_string(), owner: "alice".to_string() });
+        execute(&mut contract, Operation::Nominate { text: "Pizza".to_string(), category: None, tags: Vec::new(), owner: "alice".to_string() });
+
+        assert_eq!(contract.state.nominations.count().now_or_never().unwrap().unwrap(), 1);
+    }
+
+    #[test]
+    fn operation_count_increments_per_operation() {
+        let mut contract = voting_poll(2);
+        assert_eq!(*contract.state.operation_count.get(), 5);
+
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+        assert_eq!(*contract.state.operation_count.get(), 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "Quorum not met: 2 of 5 weight voted")]
+    fn low_weight_voters_do_not_meet_weighted_quorum() {
+        let config = PollConfig { quorum_weight: Some(5), ..PollConfig::default() };
+        let mut contract = voting_poll_with_config(2, config);
+        execute(&mut contract, Operation::AllowLateJoin { owner: "admin".to_string() });
+        execute(&mut contract, Operation::Join { name: "carol".to_string(), owner: "carol".to_string() });
+        execute(
+            &mut contract,
+            Operation::SetParticipantWeight { user_id: "carol".to_string(), weight: 4, owner: "admin".to_string() },
+        );
+        for voter in ["alice", "bob"] {
+            execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: voter.to_string() });
+        }
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+    }
+
+    #[test]
+    #[should_panic(expected = "Poll is paused")]
+    fn operations_are_blocked_while_paused() {
+        let mut contract = voting_poll(2);
+        execute(&mut contract, Operation::PausePoll { owner: "admin".to_string() });
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+    }
+
+    #[test]
+    fn resuming_restores_voting() {
+        let mut contract = voting_poll(2);
+        execute(&mut contract, Operation::PausePoll { owner: "admin".to_string() });
+        execute(&mut contract, Operation::ResumePoll { owner: "admin".to_string() });
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+
+        let ballot = contract.state.rankings.get("alice").now_or_never().unwrap().unwrap();
+        assert_eq!(ballot, Some(vec!["nom_0".to_string()]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot rank your own nomination")]
+    fn self_ranked_ballot_is_rejected_when_self_ranking_is_disabled() {
+        let config = PollConfig { allow_self_ranking: false, ..PollConfig::default() };
+        let mut contract = voting_poll_with_config(2, config);
+        execute(
+            &mut contract,
+            Operation::Vote { rankings: vec!["nom_1".to_string(), "nom_0".to_string()], owner: "alice".to_string() },
+        );
+    }
+
+    #[test]
+    fn cloned_poll_is_initialized_with_the_source_nominations() {
+        let factory_chain = ChainId(CryptoHash::test_hash("factory"));
+        let signer = AccountOwner::from(CryptoHash::test_hash("carol"));
+
+        let config = PollConfig {
+            tally_method: TallyMethod::InstantRunoff,
+            allow_write_ins: true,
+            none_of_the_above: true,
+            deadline_micros: Some(1_000),
+            ..PollConfig::default()
+        };
+        let mut source = voting_poll_with_config(2, config);
+        source.runtime.set_message_origin_chain_id(factory_chain);
+        source.execute_message(Message::RequestClone { owner: "carol".to_string() }).now_or_never().unwrap();
+        let clone_data = source.runtime.created_send_message_requests().pop().unwrap();
+        assert_eq!(clone_data.destination, factory_chain);
+
+        let runtime = ContractRuntime::new().with_application_parameters(()).with_authenticated_signer(signer);
+        let mut factory = MealVotingContract {
+            state: PollState::load(runtime.root_view_storage_context()).blocking_wait().unwrap(),
+            runtime,
+        };
+        factory.execute_message(clone_data.message).now_or_never().unwrap();
+
+        let requests = factory.runtime.created_send_message_requests();
+        let Message::InitializePoll { topic, admin_id, config, nominations, .. } = &requests[0].message else {
+            panic!("expected an InitializePoll message");
+        };
+        assert_eq!(topic, "Lunch");
+        assert_eq!(admin_id, "carol");
+        assert_eq!(config.tally_method, TallyMethod::InstantRunoff);
+        assert!(config.allow_write_ins);
+        assert!(config.none_of_the_above);
+        assert_eq!(config.deadline_micros, None);
+        assert_eq!(
+            nominations,
+            &vec![("alice".to_string(), "Pizza".to_string()), ("bob".to_string(), "Sushi".to_string())]
+        );
+    }
+
+    #[test]
+    fn none_of_the_above_winning_flags_no_acceptable_option() {
+        let config = PollConfig { none_of_the_above: true, ..PollConfig::default() };
+        let mut contract = voting_poll_with_config(2, config);
+        for voter in ["alice", "bob"] {
+            execute(
+                &mut contract,
+                Operation::Vote { rankings: vec!["nota".to_string(), "nom_1".to_string()], owner: voter.to_string() },
+            );
+        }
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+
+        assert_eq!(contract.state.results.get()[0].nomination_text, "None of the above");
+        assert!(*contract.state.no_acceptable_option.get());
+    }
+
+    #[test]
+    fn base_score_shifts_the_final_ranking() {
+        let mut contract = voting_poll(2);
+        execute(
+            &mut contract,
+            Operation::SetBaseScore { nomination_id: "nom_1".to_string(), base_score: 3, owner: "admin".to_string() },
+        );
+        for voter in ["alice", "bob"] {
+            execute(
+                &mut contract,
+                Operation::Vote { rankings: vec!["nom_0".to_string(), "nom_1".to_string()], owner: voter.to_string() },
+            );
+        }
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+
+        let results = contract.state.results.get();
+        assert_eq!(results[0].nomination_text, "Sushi");
+        assert_eq!(results[0].score, 5);
+        assert_eq!(results[1].score, 4);
+    }
+
+    #[test]
+    fn scores_saturate_instead_of_overflowing() {
+        let mut contract = voting_poll(2);
+        execute(
+            &mut contract,
+            Operation::SetBaseScore { nomination_id: "nom_0".to_string(), base_score: u64::MAX - 1, owner: "admin".to_string() },
+        );
+        for voter in ["alice", "bob"] {
+            execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: voter.to_string() });
+        }
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+
+        let results = contract.state.results.get();
+        assert_eq!(results[0].nomination_id, "nom_0");
+        assert_eq!(results[0].score, u64::MAX);
+    }
+
+    /// Creates a poll with a main-course and a dessert category, one pick allowed in each.
+    fn two_category_poll() -> MealVotingContract {
+        let budgets = ["main", "dessert"]
+            .into_iter()
+            .map(|category| CategoryBudget { category: category.to_string(), picks: 1 })
+            .collect();
+        let mut contract = create_poll_with_config(3, PollConfig { category_budgets: budgets, ..PollConfig::default() });
+        execute(&mut contract, Operation::Join { name: "alice".to_string(), owner: "alice".to_string() });
+        for (text, category) in [("Pizza", "main"), ("Sushi", "main"), ("Cake", "dessert")] {
+            execute(
+                &mut contract,
+                Operation::Nominate { text: text.to_string(), category: Some(category.to_string()), tags: Vec::new(), owner: "alice".to_string() },
+            );
+        }
+        execute(&mut contract, Operation::StartVote { owner: "admin".to_string() });
+        contract
+    }
+
+    #[test]
+    fn ballots_within_category_budgets_are_tallied_per_category() {
+        let mut contract = two_category_poll();
+        execute(
+            &mut contract,
+            Operation::Vote { rankings: vec!["nom_1".to_string(), "nom_2".to_string()], owner: "alice".to_string() },
+        );
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+
+        let mains = contract.state.category_results.get("main").now_or_never().unwrap().unwrap().unwrap();
+        assert_eq!(mains.len(), 1);
+        assert_eq!(mains[0].nomination_text, "Sushi");
+        let desserts = contract.state.category_results.get("dessert").now_or_never().unwrap().unwrap().unwrap();
+        assert_eq!(desserts[0].nomination_text, "Cake");
+    }
+
+    #[test]
+    #[should_panic(expected = "At most 1 picks allowed in category main")]
+    fn ballot_exceeding_a_category_budget_is_rejected() {
+        let mut contract = two_category_poll();
+        execute(
+            &mut contract,
+            Operation::Vote { rankings: vec!["nom_0".to_string(), "nom_1".to_string()], owner: "alice".to_string() },
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Nomination nom_0 is not on the shortlist")]
+    fn ballot_ranking_a_non_shortlisted_nomination_is_rejected() {
+        let mut contract = voting_poll(2);
+        execute(&mut contract, Operation::SetShortlist { nomination_ids: vec!["nom_1".to_string()], owner: "admin".to_string() });
+        execute(
+            &mut contract,
+            Operation::Vote { rankings: vec!["nom_1".to_string(), "nom_0".to_string()], owner: "alice".to_string() },
+        );
+    }
+
+    #[test]
+    fn closing_emits_results_computed_event() {
+        let mut contract = voting_poll(2);
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_1".to_string()], owner: "alice".to_string() });
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+
+        let events = contract.runtime.created_events();
+        let (stream, event) = events.last().expect("an event should be emitted");
+        assert_eq!(stream.0, POLL_EVENTS_STREAM);
+        assert_eq!(*event, PollEvent::ResultsComputed { results: contract.state.results.get().clone() });
+    }
+
+    fn nominate_twice(strategy: DedupStrategy) -> MealVotingContract {
+        let mut contract = create_poll_with_config(2, PollConfig { dedup_strategy: strategy, ..PollConfig::default() });
+        execute(&mut contract, Operation::Join { name: "alice".to_string(), owner: "alice".to_string() });
+        for text in ["Pizza", " pizza "] {
+            execute(&mut contract, Operation::Nominate { text: text.to_string(), category: None, tags: Vec::new(), owner: "alice".to_string() });
+        }
+        contract
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate of nomination nom_0")]
+    fn reject_strategy_refuses_duplicate_nominations() {
+        nominate_twice(DedupStrategy::Reject);
+    }
+
+    #[test]
+    fn merge_strategy_keeps_only_the_existing_nomination() {
+        let contract = nominate_twice(DedupStrategy::Merge);
+        assert_eq!(contract.state.nominations.indices().now_or_never().unwrap().unwrap(), vec!["nom_0".to_string()]);
+    }
+
+    #[test]
+    fn allow_strategy_keeps_both_nominations() {
+        let contract = nominate_twice(DedupStrategy::Allow);
+        assert_eq!(contract.state.nominations.count().now_or_never().unwrap().unwrap(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Ballot must rank 2 nominations, got 1")]
+    fn full_ranking_mode_rejects_partial_ballots() {
+        let config = PollConfig { require_full_ranking: true, ..PollConfig::default() };
+        let mut contract = voting_poll_with_config(2, config);
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+    }
+
+    #[test]
+    fn full_ranking_mode_accepts_complete_ballots() {
+        let config = PollConfig { require_full_ranking: true, ..PollConfig::default() };
+        let mut contract = voting_poll_with_config(2, config);
+        execute(
+            &mut contract,
+            Operation::Vote { rankings: vec!["nom_1".to_string(), "nom_0".to_string()], owner: "alice".to_string() },
+        );
+        assert!(contract.state.rankings.contains_key("alice").now_or_never().unwrap().unwrap());
+    }
+
+    #[test]
+    fn rejected_ballots_are_recorded_with_their_reason() {
+        let config = PollConfig { require_full_ranking: true, record_rejected_ballots: true, ..PollConfig::default() };
+        let mut contract = voting_poll_with_config(2, config);
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+
+        assert!(!contract.state.rankings.contains_key("alice").now_or_never().unwrap().unwrap());
+        let rejected = contract.state.rejected_ballots.get("alice").now_or_never().unwrap().unwrap().unwrap();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].rankings, vec!["nom_0".to_string()]);
+        assert_eq!(rejected[0].reason, "Ballot must rank 2 nominations, got 1");
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot join after voting has started")]
+    fn joining_after_start_is_rejected_by_default() {
+        let mut contract = voting_poll(2);
+        execute(&mut contract, Operation::Join { name: "carol".to_string(), owner: "carol".to_string() });
+    }
+
+    #[test]
+    fn late_joiners_can_vote_once_allowed() {
+        let mut contract = voting_poll(2);
+        execute(&mut contract, Operation::AllowLateJoin { owner: "admin".to_string() });
+        execute(&mut contract, Operation::Join { name: "carol".to_string(), owner: "carol".to_string() });
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "carol".to_string() });
+
+        assert!(contract.state.rankings.contains_key("carol").now_or_never().unwrap().unwrap());
+    }
+
+    #[test]
+    fn reaching_the_winning_score_threshold_closes_the_poll() {
+        let config = PollConfig { winning_score_threshold: Some(4), ..PollConfig::default() };
+        let mut contract = voting_poll_with_config(2, config);
+        execute(
+            &mut contract,
+            Operation::Vote { rankings: vec!["nom_1".to_string(), "nom_0".to_string()], owner: "alice".to_string() },
+        );
+        assert!(!*contract.state.is_closed.get());
+
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_1".to_string()], owner: "bob".to_string() });
+
+        assert!(*contract.state.is_closed.get());
+        let results = contract.state.results.get();
+        assert_eq!(results[0].nomination_text, "Sushi");
+        assert_eq!(results[0].score, 4);
+    }
+
+    #[test]
+    fn requested_results_are_reported_back_and_stored_by_origin() {
+        let factory_chain = ChainId(CryptoHash::test_hash("factory"));
+        let poll_chain = ChainId(CryptoHash::test_hash("poll"));
+
+        let mut poll = voting_poll(2);
+        execute(&mut poll, Operation::Vote { rankings: vec!["nom_1".to_string()], owner: "alice".to_string() });
+        execute(&mut poll, Operation::ClosePoll { owner: "admin".to_string() });
+        poll.runtime.set_message_origin_chain_id(factory_chain);
+        poll.execute_message(Message::RequestResults).now_or_never().unwrap();
+        let report = poll.runtime.created_send_message_requests().pop().unwrap();
+        assert_eq!(report.destination, factory_chain);
+
+        let runtime = ContractRuntime::new().with_application_parameters(()).with_message_origin_chain_id(poll_chain);
+        let mut factory = MealVotingContract {
+            state: PollState::load(runtime.root_view_storage_context()).blocking_wait().unwrap(),
+            runtime,
+        };
+        factory.execute_message(report.message).now_or_never().unwrap();
+
+        let stored = factory.state.poll_results.get(&poll_chain).now_or_never().unwrap().unwrap().unwrap();
+        assert_eq!(stored, *poll.state.results.get());
+    }
+
+    #[test]
+    fn reopening_a_closed_poll_allows_voting_again() {
+        let mut contract = voting_poll(2);
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+        execute(&mut contract, Operation::ReopenPoll { owner: "admin".to_string() });
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+
+        assert!(!*contract.state.is_closed.get());
+        assert!(contract.state.results.get().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Results are finalized")]
+    fn finalized_results_cannot_be_reopened() {
+        let mut contract = voting_poll(2);
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+        execute(&mut contract, Operation::FinalizeResults { owner: "admin".to_string() });
+        execute(&mut contract, Operation::ReopenPoll { owner: "admin".to_string() });
+    }
+
+    #[test]
+    fn star_ratings_are_summed_per_nomination() {
+        let mut contract = voting_poll(2);
+        for (voter, pizza, sushi) in [("alice", 2, 5), ("bob", 4, 3)] {
+            execute(
+                &mut contract,
+                Operation::RateOptions {
+                    ratings: vec![("nom_0".to_string(), pizza), ("nom_1".to_string(), sushi)],
+                    owner: voter.to_string(),
+                },
+            );
+        }
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+
+        let results = contract.state.results.get();
+        assert_eq!((results[0].nomination_text.as_str(), results[0].score), ("Sushi", 8));
+        assert_eq!((results[1].nomination_text.as_str(), results[1].score), ("Pizza", 6));
+    }
+
+    #[test]
+    #[should_panic(expected = "Ratings must be between 1 and 5 stars, got 6")]
+    fn out_of_range_star_ratings_are_rejected() {
+        let mut contract = voting_poll(2);
+        execute(
+            &mut contract,
+            Operation::RateOptions { ratings: vec![("nom_0".to_string(), 6)], owner: "alice".to_string() },
+        );
+    }
+
+    #[test]
+    fn rating_a_nomination_twice_is_rejected() {
+        let mut contract = voting_poll(2);
+        let ratings = vec![("nom_0".to_string(), 5), ("nom_0".to_string(), 5)];
+        assert_eq!(
+            try_execute(&mut contract, Operation::RateOptions { ratings, owner: "alice".to_string() }),
+            Err(MealVotingError::DuplicateRanking("nom_0".to_string()))
+        );
+        assert!(!contract.state.star_ratings.contains_key("alice").now_or_never().unwrap().unwrap());
+    }
+
+    /// Three nominations (`nom_0` A, `nom_1` B, `nom_2` C) and five ballots: A>B>C twice, C>B>A
+    /// twice and B>A>C once. Borda elects the consensus pick B; instant-runoff drops B first and
+    /// elects A.
+    fn three_way_election(tally_method: TallyMethod) -> MealVotingContract {
+        let mut contract = create_poll_with_config(3, PollConfig { tally_method, ..PollConfig::default() });
+        let voters = ["v1", "v2", "v3", "v4", "v5"];
+        for voter in voters {
+            execute(&mut contract, Operation::Join { name: voter.to_string(), owner: voter.to_string() });
+        }
+        for text in ["A", "B", "C"] {
+            execute(&mut contract, Operation::Nominate { text: text.to_string(), category: None, tags: Vec::new(), owner: "admin".to_string() });
+        }
+        execute(&mut contract, Operation::StartVote { owner: "admin".to_string() });
+        let ballots = [[0, 1, 2], [0, 1, 2], [2, 1, 0], [2, 1, 0], [1, 0, 2]];
+        for (voter, ballot) in voters.iter().zip(ballots) {
+            let rankings = ballot.iter().map(|i| format!("nom_{}", i)).collect();
+            execute(&mut contract, Operation::Vote { rankings, owner: voter.to_string() });
+        }
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+        contract
+    }
+
+    #[test]
+    fn borda_elects_the_consensus_nomination() {
+        let contract = three_way_election(TallyMethod::Borda);
+        let results = contract.state.results.get();
+        assert_eq!(results[0].nomination_id, "nom_1");
+        assert_eq!(results[0].score, 11);
+        assert!(results.iter().all(|entry| entry.eliminated_in_round.is_none()));
+    }
+
+    #[test]
+    fn instant_runoff_eliminates_the_fewest_first_choices_each_round() {
+        let contract = three_way_election(TallyMethod::InstantRunoff);
+        let results = contract.state.results.get();
+        let order: Vec<_> = results.iter().map(|entry| (entry.nomination_id.as_str(), entry.score, entry.eliminated_in_round)).collect();
+        assert_eq!(order, vec![("nom_0", 3, None), ("nom_2", 2, None), ("nom_1", 1, Some(1))]);
+    }
+
+    #[test]
+    fn a_ballot_after_the_deadline_closes_the_poll_without_being_counted() {
+        let runtime = ContractRuntime::new().with_application_parameters(()).with_system_time(Timestamp::from(0));
+        let config = PollConfig { deadline_micros: Some(1_000), ..PollConfig::default() };
+        let mut contract = create_poll_on(runtime, 2, config);
+        for name in ["alice", "bob"] {
+            execute(&mut contract, Operation::Join { name: name.to_string(), owner: name.to_string() });
+        }
+        execute(&mut contract, Operation::Nominate { text: "Pizza".to_string(), category: None, tags: Vec::new(), owner: "alice".to_string() });
+        execute(&mut contract, Operation::StartVote { owner: "admin".to_string() });
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+
+        contract.runtime.set_system_time(Timestamp::from(1_000));
+        assert_eq!(
+            try_execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "bob".to_string() }),
+            Ok(())
+        );
+
+        assert!(*contract.state.is_closed.get());
+        assert!(!contract.state.rankings.contains_key("bob").now_or_never().unwrap().unwrap());
+        assert_eq!(contract.state.results.get()[0].score, 2);
+        assert_eq!(
+            try_execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "bob".to_string() }),
+            Err(MealVotingError::PollClosed)
+        );
+    }
+
+    #[test]
+    fn a_ballot_message_after_the_deadline_closes_the_poll_too() {
+        let runtime = ContractRuntime::new().with_application_parameters(()).with_system_time(Timestamp::from(0));
+        let config = PollConfig { deadline_micros: Some(1_000), ..PollConfig::default() };
+        let mut contract = create_poll_on(runtime, 2, config);
+        for name in ["alice", "bob"] {
+            execute(&mut contract, Operation::Join { name: name.to_string(), owner: name.to_string() });
+        }
+        execute(&mut contract, Operation::Nominate { text: "Pizza".to_string(), category: None, tags: Vec::new(), owner: "alice".to_string() });
+        execute(&mut contract, Operation::StartVote { owner: "admin".to_string() });
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+
+        contract.runtime.set_system_time(Timestamp::from(2_000));
+        let late = Message::Vote { user_id: "bob".to_string(), rankings: vec!["nom_0".to_string()] };
+        assert_eq!(contract.try_execute_message(late).now_or_never().unwrap(), Ok(()));
+
+        assert!(*contract.state.is_closed.get());
+        assert!(!contract.state.rankings.contains_key("bob").now_or_never().unwrap().unwrap());
+        assert_eq!(contract.state.results.get()[0].score, 2);
+    }
+
+    #[test]
+    fn removing_a_participant_drops_their_nominations() {
+        let mut contract = create_poll_with_config(2, PollConfig::default());
+        for name in ["alice", "bob"] {
+            execute(&mut contract, Operation::Join { name: name.to_string(), owner: name.to_string() });
+        }
+        execute(&mut contract, Operation::Nominate { text: "Pizza".to_string(), category: None, tags: Vec::new(), owner: "alice".to_string() });
+        execute(&mut contract, Operation::Nominate { text: "Sushi".to_string(), category: None, tags: Vec::new(), owner: "bob".to_string() });
+
+        execute(&mut contract, Operation::RemoveParticipant { target: "bob".to_string(), owner: "admin".to_string() });
+
+        assert_eq!(contract.state.participants.count().now_or_never().unwrap().unwrap(), 2);
+        assert!(!contract.state.participants.contains_key("bob").now_or_never().unwrap().unwrap());
+        assert_eq!(contract.state.nominations.indices().now_or_never().unwrap().unwrap(), vec!["nom_0".to_string()]);
+    }
+
+    #[test]
+    fn joins_are_refused_once_max_participants_is_reached() {
+        let config = PollConfig { max_participants: Some(3), ..PollConfig::default() };
+        let mut contract = create_poll_with_config(2, config);
+        execute(&mut contract, Operation::Join { name: "alice".to_string(), owner: "alice".to_string() });
+        execute(&mut contract, Operation::Join { name: "bob".to_string(), owner: "bob".to_string() });
+        assert_eq!(contract.state.participants.count().now_or_never().unwrap().unwrap(), 3);
+
+        assert_eq!(
+            try_execute(&mut contract, Operation::Join { name: "carol".to_string(), owner: "carol".to_string() }),
+            Err(MealVotingError::PollFull(3))
+        );
+        execute(&mut contract, Operation::Join { name: "Admin".to_string(), owner: "admin".to_string() });
+    }
+
+    #[test]
+    fn ballots_with_unknown_or_repeated_nominations_are_rejected() {
+        let mut contract = voting_poll(2);
+        assert_eq!(
+            try_execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string(), "bogus".to_string()], owner: "alice".to_string() }),
+            Err(MealVotingError::UnknownNomination("bogus".to_string()))
+        );
+        assert_eq!(
+            try_execute(&mut contract, Operation::Vote { rankings: vec!["nom_1".to_string(), "nom_1".to_string()], owner: "alice".to_string() }),
+            Err(MealVotingError::DuplicateRanking("nom_1".to_string()))
+        );
+        assert!(!contract.state.rankings.contains_key("alice").now_or_never().unwrap().unwrap());
+    }
+
+    #[test]
+    fn tied_results_are_ordered_by_nomination_id() {
+        let entry = |id: &str| ResultEntry {
+            nomination_id: id.to_string(),
+            nomination_text: id.to_string(),
+            score: 3,
+            eliminated_in_round: None,
+            first_choice_votes: 0,
+            elected: false,
+        };
+        let mut forward = vec![entry("nom_0"), entry("nom_1")];
+        let mut backward = vec![entry("nom_1"), entry("nom_0")];
+        sort_results(&mut forward, None);
+        sort_results(&mut backward, None);
+
+        assert_eq!(forward, backward);
+        assert_eq!(forward[0].nomination_id, "nom_0");
+    }
+
+    #[test]
+    fn empty_ballots_are_recorded_as_abstentions_and_count_toward_quorum() {
+        let config = PollConfig { quorum_weight: Some(2), require_full_ranking: true, ..PollConfig::default() };
+        let mut contract = voting_poll_with_config(2, config);
+        execute(&mut contract, Operation::Vote { rankings: Vec::new(), owner: "alice".to_string() });
+        execute(&mut contract, Operation::Vote { rankings: Vec::new(), owner: "bob".to_string() });
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string(), "nom_1".to_string()], owner: "bob".to_string() });
+
+        assert_eq!(*contract.state.abstentions.get(), 1);
+        assert_eq!(contract.state.rankings.get("alice").now_or_never().unwrap().unwrap(), Some(Vec::new()));
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+        assert!(*contract.state.is_closed.get());
+    }
+
+    #[test]
+    fn reopening_nominations_allows_nominating_and_purges_ballots() {
+        let mut contract = voting_poll(2);
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+
+        execute(&mut contract, Operation::ReopenNominations { owner: "admin".to_string() });
+        execute(&mut contract, Operation::Nominate { text: "Tacos".to_string(), category: None, tags: Vec::new(), owner: "bob".to_string() });
+
+        assert!(!*contract.state.has_started.get());
+        assert_eq!(contract.state.rankings.count().now_or_never().unwrap().unwrap(), 0);
+        assert!(contract.state.nominations.contains_key("nom_2").now_or_never().unwrap().unwrap());
+    }
+
+    fn nominate_pizza_as_alice_and_bob(config: PollConfig) -> Result<MealVotingContract, MealVotingError> {
+        let mut contract = create_poll_with_config(2, config);
+        for name in ["alice", "bob"] {
+            execute(&mut contract, Operation::Join { name: name.to_string(), owner: name.to_string() });
+        }
+        execute(&mut contract, Operation::Nominate { text: "Pizza".to_string(), category: None, tags: Vec::new(), owner: "alice".to_string() });
+        try_execute(&mut contract, Operation::Nominate { text: "  PIZZA".to_string(), category: None, tags: Vec::new(), owner: "bob".to_string() })?;
+        Ok(contract)
+    }
+
+    #[test]
+    fn duplicate_nominations_are_rejected_by_default() {
+        let result = nominate_pizza_as_alice_and_bob(PollConfig::default());
+        assert_eq!(result.err(), Some(MealVotingError::DuplicateNomination("nom_0".to_string())));
+    }
+
+    #[test]
+    fn merged_duplicates_endorse_the_existing_nomination() {
+        let config = PollConfig { dedup_strategy: DedupStrategy::Merge, ..PollConfig::default() };
+        let contract = nominate_pizza_as_alice_and_bob(config).unwrap();
+
+        assert_eq!(contract.state.nominations.count().now_or_never().unwrap().unwrap(), 1);
+        assert_eq!(
+            contract.state.endorsements.get("nom_0").now_or_never().unwrap().unwrap(),
+            Some(vec!["bob".to_string()])
+        );
+    }
+
+    #[test]
+    fn a_double_weight_voter_flips_the_result() {
+        let ballots = [("alice", vec!["nom_0"]), ("bob", vec!["nom_1"]), ("admin", vec!["nom_0", "nom_1"])];
+        let winner = |bob_weight: u64| {
+            let mut contract = voting_poll(2);
+            execute(&mut contract, Operation::SetParticipantWeight { user_id: "bob".to_string(), weight: bob_weight, owner: "admin".to_string() });
+            for (voter, ballot) in &ballots {
+                let rankings = ballot.iter().map(|id| id.to_string()).collect();
+                execute(&mut contract, Operation::Vote { rankings, owner: voter.to_string() });
+            }
+            execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+            contract.state.results.get()[0].clone()
+        };
+
+        let (uniform, weighted) = (winner(1), winner(2));
+        assert_eq!((uniform.nomination_id.as_str(), uniform.score), ("nom_0", 4));
+        assert_eq!((weighted.nomination_id.as_str(), weighted.score), ("nom_1", 5));
+    }
+
+    #[test]
+    fn a_poll_lifecycle_emits_transition_events() {
+        let mut contract = voting_poll(2);
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+
+        let events: Vec<PollEvent> = contract.runtime.created_events().iter().map(|(_, event)| event.clone()).collect();
+        assert_eq!(
+            events,
+            vec![
+                PollEvent::PollCreated { topic: "Lunch".to_string(), admin_id: "admin".to_string() },
+                PollEvent::ParticipantJoined { user_id: "alice".to_string() },
+                PollEvent::ParticipantJoined { user_id: "bob".to_string() },
+                PollEvent::VotingStarted,
+                PollEvent::PollClosed,
+                PollEvent::ResultsComputed { results: Vec::new() },
+            ]
+        );
+    }
+
+    #[test]
+    fn transferring_admin_moves_the_privileges() {
+        let mut contract = voting_poll(2);
+        execute(&mut contract, Operation::TransferAdmin { new_admin: "alice".to_string(), owner: "admin".to_string() });
+
+        assert_eq!(
+            try_execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() }),
+            Err(MealVotingError::NotAdmin("close the poll"))
+        );
+        execute(&mut contract, Operation::ClosePoll { owner: "alice".to_string() });
+        assert!(*contract.state.is_closed.get());
+    }
+
+    #[test]
+    fn closing_below_quorum_computes_results_but_flags_them() {
+        let config = PollConfig { quorum: 2, ..PollConfig::default() };
+        let mut contract = voting_poll_with_config(2, config);
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+
+        assert!(!*contract.state.quorum_met.get());
+        assert_eq!(contract.state.results.get()[0].nomination_id, "nom_0");
+    }
+
+    #[test]
+    fn closing_at_quorum_sets_quorum_met() {
+        let config = PollConfig { quorum: 2, ..PollConfig::default() };
+        let mut contract = voting_poll_with_config(2, config);
+        for voter in ["alice", "bob"] {
+            execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: voter.to_string() });
+        }
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+
+        assert!(*contract.state.quorum_met.get());
+    }
+
+    #[test]
+    fn results_count_first_choice_votes() {
+        let contract = three_way_election(TallyMethod::Borda);
+        let first_choices: BTreeMap<_, _> = contract
+            .state
+            .results
+            .get()
+            .iter()
+            .map(|entry| (entry.nomination_id.clone(), entry.first_choice_votes))
+            .collect();
+
+        let expected = [("nom_0", 2), ("nom_1", 1), ("nom_2", 2)].map(|(id, votes)| (id.to_string(), votes));
+        assert_eq!(first_choices, BTreeMap::from(expected));
+        assert_eq!(first_choices.values().sum::<u64>(), 5);
+    }
+
+    #[test]
+    fn initialize_poll_carries_description_and_category() {
+        let config = PollConfig {
+            description: "Friday team lunch, budget 15 each".to_string(),
+            category: "Team lunch".to_string(),
+            ..PollConfig::default()
+        };
+        let contract = create_poll_with_config(2, config);
+
+        assert_eq!(contract.state.description.get(), "Friday team lunch, budget 15 each");
+        assert_eq!(contract.state.category.get(), "Team lunch");
+        assert_eq!(create_poll_with_config(2, PollConfig::default()).state.description.get(), "");
+    }
+
+    #[test]
+    fn ballots_shorter_than_min_rankings_are_rejected() {
+        let mut contract = create_poll_with_config(3, PollConfig { min_rankings: 2, ..PollConfig::default() });
+        execute(&mut contract, Operation::Join { name: "alice".to_string(), owner: "alice".to_string() });
+        for text in ["Pizza", "Sushi", "Tacos"] {
+            execute(&mut contract, Operation::Nominate { text: text.to_string(), category: None, tags: Vec::new(), owner: "alice".to_string() });
+        }
+        execute(&mut contract, Operation::StartVote { owner: "admin".to_string() });
+        let ballot = |len: usize| (0..len).map(|i| format!("nom_{}", i)).collect::<Vec<_>>();
+
+        assert_eq!(
+            try_execute(&mut contract, Operation::Vote { rankings: ballot(1), owner: "alice".to_string() }),
+            Err(MealVotingError::IncompleteBallot { required: 2, ranked: 1 })
+        );
+        assert_eq!(try_execute(&mut contract, Operation::Vote { rankings: ballot(2), owner: "alice".to_string() }), Ok(()));
+        assert_eq!(try_execute(&mut contract, Operation::Vote { rankings: ballot(3), owner: "alice".to_string() }), Ok(()));
+        assert_eq!(try_execute(&mut contract, Operation::Vote { rankings: Vec::new(), owner: "alice".to_string() }), Ok(()));
+    }
+
+    #[test]
+    fn joining_a_poll_is_reported_to_the_creating_chain() {
+        let factory_chain = ChainId(CryptoHash::test_hash("factory"));
+        let poll_chain = ChainId(CryptoHash::test_hash("poll"));
+
+        let runtime = ContractRuntime::new()
+            .with_application_parameters(())
+            .with_message_origin_chain_id(factory_chain);
+        let mut poll = create_poll_on(runtime, 2, PollConfig::default());
+        execute(&mut poll, Operation::Join { name: "alice".to_string(), owner: "alice".to_string() });
+        execute(&mut poll, Operation::Join { name: "alice".to_string(), owner: "alice".to_string() });
+        let mut notices = poll.runtime.created_send_message_requests();
+        assert_eq!(notices.len(), 1);
+        let notice = notices.pop().unwrap();
+        assert_eq!(notice.destination, factory_chain);
+
+        let runtime = ContractRuntime::new().with_application_parameters(()).with_message_origin_chain_id(poll_chain);
+        let mut factory = MealVotingContract {
+            state: PollState::load(runtime.root_view_storage_context()).blocking_wait().unwrap(),
+            runtime,
+        };
+        factory.execute_message(notice.message.clone()).now_or_never().unwrap();
+        factory.execute_message(notice.message).now_or_never().unwrap();
+
+        let joined = factory.state.joined_polls.get("alice").now_or_never().unwrap().unwrap().unwrap();
+        assert_eq!(joined, vec![poll_chain]);
+        assert!(factory.state.created_polls.get("alice").now_or_never().unwrap().unwrap().is_none());
+    }
+
+    #[test]
+    fn approval_counts_one_point_per_approved_nomination() {
+        let scores = |tally_method| {
+            let mut contract = voting_poll_with_config(2, PollConfig { tally_method, ..PollConfig::default() });
+            execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string(), "nom_1".to_string()], owner: "alice".to_string() });
+            execute(&mut contract, Operation::Vote { rankings: vec!["nom_1".to_string(), "nom_0".to_string()], owner: "bob".to_string() });
+            execute(&mut contract, Operation::Vote { rankings: vec!["nom_1".to_string()], owner: "admin".to_string() });
+            execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+            contract.state.results.get().iter().map(|entry| (entry.nomination_id.clone(), entry.score)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(scores(TallyMethod::Borda), vec![("nom_1".to_string(), 5), ("nom_0".to_string(), 3)]);
+        assert_eq!(scores(TallyMethod::Approval), vec![("nom_1".to_string(), 3), ("nom_0".to_string(), 2)]);
+    }
+
+    #[test]
+    fn condorcet_winner_is_recorded_at_close() {
+        let contract = three_way_election(TallyMethod::Borda);
+        assert_eq!(*contract.state.condorcet_winner.get(), Some("nom_1".to_string()));
+    }
+
+    #[test]
+    fn condorcet_cycle_has_no_winner() {
+        let mut contract = create_poll_with_config(3, PollConfig::default());
+        let voters = ["v1", "v2", "v3"];
+        for voter in voters {
+            execute(&mut contract, Operation::Join { name: voter.to_string(), owner: voter.to_string() });
+        }
+        for text in ["Rock", "Paper", "Scissors"] {
+            execute(&mut contract, Operation::Nominate { text: text.to_string(), category: None, tags: Vec::new(), owner: "admin".to_string() });
+        }
+        execute(&mut contract, Operation::StartVote { owner: "admin".to_string() });
+        let ballots = [[0, 2, 1], [1, 0, 2], [2, 1, 0]];
+        for (voter, ballot) in voters.iter().zip(ballots) {
+            let rankings = ballot.iter().map(|i| format!("nom_{}", i)).collect();
+            execute(&mut contract, Operation::Vote { rankings, owner: voter.to_string() });
+        }
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+
+        assert_eq!(*contract.state.condorcet_winner.get(), None);
+    }
+
+    #[test]
+    fn rename_before_join_is_rejected() {
+        let mut contract = create_poll_with_config(2, PollConfig::default());
+        assert_eq!(
+            try_execute(&mut contract, Operation::Rename { name: "Al".to_string(), owner: "alice".to_string() }),
+            Err(MealVotingError::NotAParticipant)
+        );
+    }
+
+    #[test]
+    fn rename_after_join_updates_the_name_and_rejoin_keeps_it() {
+        let mut contract = create_poll_with_config(2, PollConfig::default());
+        execute(&mut contract, Operation::Join { name: "alice".to_string(), owner: "alice".to_string() });
+        execute(&mut contract, Operation::Rename { name: "Al".to_string(), owner: "alice".to_string() });
+        execute(&mut contract, Operation::Join { name: "alice".to_string(), owner: "alice".to_string() });
+
+        let name = contract.state.participants.get("alice").now_or_never().unwrap().unwrap();
+        assert_eq!(name, Some("Al".to_string()));
+        let events: Vec<PollEvent> = contract.runtime.created_events().iter().map(|(_, event)| event.clone()).collect();
+        assert_eq!(
+            events[1..],
+            [
+                PollEvent::ParticipantJoined { user_id: "alice".to_string() },
+                PollEvent::ParticipantRenamed { user_id: "alice".to_string(), name: "Al".to_string() },
+                PollEvent::ParticipantRejoined { user_id: "alice".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn ballots_record_when_they_were_cast() {
+        let mut contract = voting_poll(2);
+        contract.runtime.set_system_time(Timestamp::from(5_000));
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+        contract.runtime.set_system_time(Timestamp::from(7_000));
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_1".to_string()], owner: "alice".to_string() });
+
+        let voted_at = contract.state.ranking_times.get("alice").now_or_never().unwrap().unwrap();
+        assert_eq!(voted_at, Some(Timestamp::from(7_000)));
+    }
+
+    #[test]
+    fn resubmitted_ballots_count_as_revisions() {
+        let mut contract = voting_poll(2);
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_1".to_string()], owner: "alice".to_string() });
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_1".to_string()], owner: "bob".to_string() });
+
+        let revisions = |user_id: &str| contract.state.vote_revisions.get(user_id).now_or_never().unwrap().unwrap();
+        assert_eq!(revisions("alice"), Some(2));
+        assert_eq!(revisions("bob"), Some(1));
+
+        assert_eq!(contract.state.winner_explanation().now_or_never().unwrap(), None);
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+        assert_eq!(
+            contract.state.winner_explanation().now_or_never().unwrap().as_deref(),
+            Some("Sushi won with a score of 4 (2 first-choice votes); ballots were changed 1 times before the close")
+        );
+    }
+
+    #[test]
+    fn nominations_beyond_the_per_user_limit_are_rejected() {
+        let mut contract = create_poll_with_config(2, PollConfig { max_nominations_per_user: 2, ..PollConfig::default() });
+        execute(&mut contract, Operation::Join { name: "alice".to_string(), owner: "alice".to_string() });
+        for text in ["Pizza", "Sushi"] {
+            execute(&mut contract, Operation::Nominate { text: text.to_string(), category: None, tags: Vec::new(), owner: "alice".to_string() });
+        }
+        execute(&mut contract, Operation::Nominate { text: "Curry".to_string(), category: None, tags: Vec::new(), owner: "admin".to_string() });
+
+        assert_eq!(
+            try_execute(
+                &mut contract,
+                Operation::Nominate { text: "Tacos".to_string(), category: None, tags: Vec::new(), owner: "alice".to_string() }
+            ),
+            Err(MealVotingError::NominationLimitReached(2))
+        );
+    }
+
+    fn two_seat_election(tally_method: TallyMethod) -> Vec<ResultEntry> {
+        let config = PollConfig { tally_method, multi_winner: 2, ..PollConfig::default() };
+        let mut contract = create_poll_with_config(3, config);
+        let voters = ["v1", "v2", "v3", "v4", "v5", "v6", "v7"];
+        for voter in voters {
+            execute(&mut contract, Operation::Join { name: voter.to_string(), owner: voter.to_string() });
+        }
+        for text in ["A", "B", "C"] {
+            execute(&mut contract, Operation::Nominate { text: text.to_string(), category: None, tags: Vec::new(), owner: "admin".to_string() });
+        }
+        execute(&mut contract, Operation::StartVote { owner: "admin".to_string() });
+        let ballots = [[0, 1, 2], [0, 1, 2], [0, 1, 2], [0, 1, 2], [2, 1, 0], [2, 1, 0], [2, 1, 0]];
+        for (voter, ballot) in voters.iter().zip(ballots) {
+            let rankings = ballot.iter().map(|i| format!("nom_{}", i)).collect();
+            execute(&mut contract, Operation::Vote { rankings, owner: voter.to_string() });
+        }
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+        contract.state.results.get().clone()
+    }
+
+    #[test]
+    fn stv_elects_a_different_pair_than_top_two_borda() {
+        let borda = two_seat_election(TallyMethod::Borda);
+        let top_two: Vec<_> = borda.iter().take(2).map(|entry| entry.nomination_id.as_str()).collect();
+        assert_eq!(top_two, vec!["nom_0", "nom_1"]);
+
+        // Droop quota is 3: A is elected with 4 and its surplus of 1 moves to B, then C's 3 meet it.
+        let stv = two_seat_election(TallyMethod::Stv);
+        let elected: Vec<_> = stv.iter().filter(|entry| entry.elected).map(|entry| entry.nomination_id.as_str()).collect();
+        assert_eq!(elected, vec!["nom_0", "nom_2"]);
+        assert_eq!((stv[2].nomination_id.as_str(), stv[2].score, stv[2].elected), ("nom_1", 1, false));
+    }
+
+    #[test]
+    fn stv_eliminates_the_weakest_when_nobody_reaches_the_quota() {
+        let mut ballots = vec![vec!["a".to_string()]; 3];
+        ballots.extend(vec![vec!["b".to_string()]; 2]);
+        ballots.extend(vec![vec!["c".to_string(), "b".to_string()]; 2]);
+        let candidates = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+
+        // Quota for one seat of seven ballots is 4: c loses the tie with b and its ballots lift b to 4.
+        let count = single_transferable_vote(candidates, &ballots, 1);
+        assert_eq!(count.eliminations.get("c"), Some(&1));
+        assert_eq!(count.elected.into_iter().collect::<Vec<_>>(), vec!["b".to_string()]);
+    }
+
+    #[test]
+    fn stv_quota_ignores_abstentions() {
+        let mut ballots = vec![vec!["a".to_string()]; 2];
+        ballots.push(vec!["b".to_string()]);
+        ballots.extend(vec![Vec::new(); 3]);
+        let candidates = vec!["a".to_string(), "b".to_string()];
+
+        // Quota for one seat of three cast ballots is 2, so a wins outright and b is never
+        // eliminated; counting the three abstentions would have pushed the quota to 4.
+        let count = single_transferable_vote(candidates, &ballots, 1);
+        assert!(count.eliminations.is_empty());
+        assert_eq!(count.elected.into_iter().collect::<Vec<_>>(), vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn create_poll_validates_its_settings() {
+        let signer = AccountOwner::from(CryptoHash::test_hash("carol"));
+        let runtime = ContractRuntime::new().with_application_parameters(()).with_authenticated_signer(signer);
+        let mut factory = MealVotingContract {
+            state: PollState::load(runtime.root_view_storage_context()).blocking_wait().unwrap(),
+            runtime,
+        };
+        let create_with = |topic: &str, votes_per_voter, config| Operation::CreatePoll {
+            topic: topic.to_string(),
+            votes_per_voter,
+            owner: "carol".to_string(),
+            config,
+        };
+        let create = |topic: &str, votes_per_voter| create_with(topic, votes_per_voter, PollConfig::default());
+
+        assert_eq!(try_execute(&mut factory, create("Lunch", 0)), Err(MealVotingError::ZeroVotesPerVoter));
+        assert_eq!(
+            try_execute(&mut factory, create("  ", 2)),
+            Err(MealVotingError::InvalidConfiguration("topic must not be empty"))
+        );
+        assert_eq!(
+            try_execute(&mut factory, create_with("Lunch", 2, PollConfig { min_rankings: 3, ..PollConfig::default() })),
+            Err(MealVotingError::InvalidConfiguration("min rankings must not exceed votes per voter"))
+        );
+        assert_eq!(
+            try_execute(&mut factory, create_with("Lunch", 2, PollConfig { max_points_per_option: Some(0), ..PollConfig::default() })),
+            Err(MealVotingError::InvalidConfiguration("max points per option must be at least 1"))
+        );
+        let config = PollConfig { allow_write_ins: true, allow_self_ranking: false, ..PollConfig::default() };
+        assert_eq!(
+            try_execute(&mut factory, create_with("Lunch", 2, config)),
+            Err(MealVotingError::InvalidConfiguration("write-ins need self-ranking to be allowed"))
+        );
+        let config = PollConfig { opens_at: Some(Timestamp::from(500)), deadline_micros: Some(500), ..PollConfig::default() };
+        assert_eq!(
+            try_execute(&mut factory, create_with("Lunch", 2, config)),
+            Err(MealVotingError::InvalidConfiguration("deadline must be after the opening time"))
+        );
+        assert!(factory.runtime.created_send_message_requests().is_empty());
+
+        execute(&mut factory, create("Lunch", 2));
+        let created = factory.state.created_polls.get("carol").now_or_never().unwrap().unwrap().unwrap();
+        assert_eq!(created.len(), 1);
+    }
+
+    #[test]
+    fn cancelled_polls_reject_voting_and_closing() {
+        let mut contract = voting_poll(2);
+        assert_eq!(
+            try_execute(&mut contract, Operation::CancelPoll { owner: "alice".to_string() }),
+            Err(MealVotingError::NotAdmin("cancel the poll"))
+        );
+        execute(&mut contract, Operation::CancelPoll { owner: "admin".to_string() });
+
+        assert_eq!(
+            try_execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() }),
+            Err(MealVotingError::PollCancelled)
+        );
+        assert_eq!(
+            try_execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() }),
+            Err(MealVotingError::PollCancelled)
+        );
+        assert_eq!(
+            contract.try_execute_message(Message::ClosePoll { user_id: "admin".to_string() }).now_or_never().unwrap(),
+            Err(MealVotingError::PollCancelled)
+        );
+        assert!(*contract.state.is_cancelled.get());
+        assert!(!*contract.state.is_closed.get());
+        assert!(contract.state.results.get().is_empty());
+    }
+
+    #[test]
+    fn coordinator_merges_partial_results_from_shards() {
+        let coordinator_chain = ChainId(CryptoHash::test_hash("coordinator"));
+        let mut submissions = Vec::new();
+        let shards = [("shard a", vec![vec!["nom_0", "nom_1"]]), ("shard b", vec![vec!["nom_1"], vec!["nom_1", "nom_0"]])];
+        for (shard, ballots) in shards {
+            let mut poll = voting_poll(2);
+            for (voter, ballot) in ["alice", "bob"].iter().zip(ballots) {
+                let rankings = ballot.iter().map(|id| id.to_string()).collect();
+                execute(&mut poll, Operation::Vote { rankings, owner: voter.to_string() });
+            }
+            execute(&mut poll, Operation::ClosePoll { owner: "admin".to_string() });
+            execute(&mut poll, Operation::SubmitPartialResults { coordinator: coordinator_chain, owner: "admin".to_string() });
+            let submission = poll.runtime.created_send_message_requests().pop().unwrap();
+            assert_eq!(submission.destination, coordinator_chain);
+            submissions.push((ChainId(CryptoHash::test_hash(shard)), submission.message));
+        }
+
+        let mut coordinator = voting_poll(2);
+        let rankings = vec!["nom_0".to_string(), "nom_1".to_string()];
+        execute(&mut coordinator, Operation::Vote { rankings, owner: "alice".to_string() });
+
+        let (first_shard, first_message) = submissions[0].clone();
+        coordinator.runtime.set_message_origin_chain_id(first_shard);
+        assert_eq!(
+            coordinator.try_execute_message(first_message.clone()).now_or_never().unwrap(),
+            Err(MealVotingError::UnregisteredShard)
+        );
+        assert!(coordinator.state.results.get().is_empty());
+
+        for (shard, _) in &submissions {
+            execute(&mut coordinator, Operation::RegisterShard { shard: *shard, owner: "admin".to_string() });
+        }
+        for (shard, message) in submissions.iter().chain(&submissions[..1]) {
+            coordinator.runtime.set_message_origin_chain_id(*shard);
+            coordinator.execute_message(message.clone()).now_or_never().unwrap();
+        }
+
+        // Local: Pizza 2, Sushi 1. Shard a: Pizza 2, Sushi 1. Shard b: Sushi 4, Pizza 1.
+        // Shard a's re-send is not counted twice.
+        let totals = |coordinator: &MealVotingContract| -> Vec<(String, u64, u64)> {
+            coordinator
+                .state
+                .results
+                .get()
+                .iter()
+                .map(|entry| (entry.nomination_id.clone(), entry.score, entry.first_choice_votes))
+                .collect()
+        };
+        let expected = vec![("nom_1".to_string(), 6, 2), ("nom_0".to_string(), 5, 2)];
+        assert_eq!(totals(&coordinator), expected);
+
+        // Closing keeps the shards' results; once finalized, no shard can change them.
+        execute(&mut coordinator, Operation::ClosePoll { owner: "admin".to_string() });
+        assert_eq!(totals(&coordinator), expected);
+        execute(&mut coordinator, Operation::FinalizeResults { owner: "admin".to_string() });
+        coordinator.runtime.set_message_origin_chain_id(first_shard);
+        assert_eq!(
+            coordinator.try_execute_message(first_message).now_or_never().unwrap(),
+            Err(MealVotingError::ResultsFinalized)
+        );
+    }
+
+    #[test]
+    fn control_messages_from_other_chains_are_rejected() {
+        let factory_chain = ChainId(CryptoHash::test_hash("factory"));
+        let runtime = ContractRuntime::new()
+            .with_application_parameters(())
+            .with_message_origin_chain_id(factory_chain);
+        let mut contract = create_poll_on(runtime, 2, PollConfig::default());
+
+        contract.runtime.set_message_origin_chain_id(ChainId(CryptoHash::test_hash("mallory")));
+        assert_eq!(
+            contract.try_execute_message(Message::ClosePoll { user_id: "admin".to_string() }).now_or_never().unwrap(),
+            Err(MealVotingError::UntrustedOrigin)
+        );
+        assert!(!*contract.state.is_closed.get());
+
+        contract.runtime.set_message_origin_chain_id(factory_chain);
+        contract.try_execute_message(Message::ClosePoll { user_id: "admin".to_string() }).now_or_never().unwrap().unwrap();
+        assert!(*contract.state.is_closed.get());
+    }
+
+    #[test]
+    fn votes_per_voter_cannot_drop_below_min_rankings() {
+        let config = PollConfig { min_rankings: 2, ballot_limit_policy: BallotLimitPolicy::Truncate, ..PollConfig::default() };
+        let mut contract = voting_poll_with_config(3, config);
+        let rankings = vec!["nom_0".to_string(), "nom_1".to_string()];
+        execute(&mut contract, Operation::Vote { rankings: rankings.clone(), owner: "alice".to_string() });
+
+        assert_eq!(
+            try_execute(&mut contract, Operation::SetVotesPerVoter { votes_per_voter: 1, owner: "admin".to_string() }),
+            Err(MealVotingError::InvalidConfiguration("min rankings must not exceed votes per voter"))
+        );
+        assert_eq!(*contract.state.votes_per_voter.get(), 3);
+        assert_eq!(contract.state.rankings.get("alice").now_or_never().unwrap().unwrap(), Some(rankings));
+
+        execute(&mut contract, Operation::SetVotesPerVoter { votes_per_voter: 2, owner: "admin".to_string() });
+        assert_eq!(*contract.state.votes_per_voter.get(), 2);
+    }
+
+    #[test]
+    fn refused_removals_and_messages_return_their_error_variant() {
+        let mut contract = create_poll_with_config(2, PollConfig::default());
+        execute(&mut contract, Operation::Join { name: "alice".to_string(), owner: "alice".to_string() });
+        assert_eq!(
+            try_execute(&mut contract, Operation::RemoveParticipant { target: "admin".to_string(), owner: "admin".to_string() }),
+            Err(MealVotingError::CannotRemoveAdmin)
+        );
+        execute(&mut contract, Operation::Nominate { text: "Pizza".to_string(), category: None, tags: Vec::new(), owner: "alice".to_string() });
+        execute(&mut contract, Operation::StartVote { owner: "admin".to_string() });
+        assert_eq!(
+            try_execute(&mut contract, Operation::RemoveParticipant { target: "alice".to_string(), owner: "admin".to_string() }),
+            Err(MealVotingError::RemovalClosed)
+        );
+
+        // The mock runtime has neither an origin chain nor a signer unless a test sets them.
+        assert_eq!(
+            contract.try_execute_message(Message::RequestResults).now_or_never().unwrap(),
+            Err(MealVotingError::MissingOrigin)
+        );
+        let clone = Message::CloneData {
+            topic: "Lunch".to_string(),
+            votes_per_voter: 2,
+            config: Box::new(PollConfig::default()),
+            nominations: Vec::new(),
+            owner: "alice".to_string(),
+        };
+        assert_eq!(contract.try_execute_message(clone).now_or_never().unwrap(), Err(MealVotingError::MissingSigner));
+
+        // Storage failures can't be provoked in the mock, so check how a failed join reads.
+        assert_eq!(
+            MealVotingError::JoinFailed("storage unavailable".to_string()).to_string(),
+            "Failed to record participant: storage unavailable"
+        );
+    }
+
+    #[test]
+    fn finalized_polls_refuse_state_changing_messages() {
+        let mut contract = voting_poll(2);
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+        execute(&mut contract, Operation::FinalizeResults { owner: "admin".to_string() });
+        let results = contract.state.results.get().clone();
+
+        for message in [
+            Message::Vote { user_id: "bob".to_string(), rankings: vec!["nom_1".to_string()] },
+            Message::Nominate { user_id: "bob".to_string(), text: "Tacos".to_string(), category: None, tags: Vec::new() },
+            Message::TransferAdmin { user_id: "admin".to_string(), new_admin: "bob".to_string() },
+            Message::CancelPoll { user_id: "admin".to_string() },
+        ] {
+            assert_eq!(
+                contract.try_execute_message(message).now_or_never().unwrap(),
+                Err(MealVotingError::ResultsFinalized)
+            );
+        }
+        assert_eq!(contract.state.admin_id.get(), "admin");
+        assert!(!*contract.state.is_cancelled.get());
+        assert!(!contract.state.rankings.contains_key("bob").now_or_never().unwrap().unwrap());
+        assert_eq!(*contract.state.results.get(), results);
+
+        contract.runtime.set_message_origin_chain_id(ChainId(CryptoHash::test_hash("factory")));
+        assert_eq!(contract.try_execute_message(Message::RequestResults).now_or_never().unwrap(), Ok(()));
+    }
+
+    #[test]
+    fn a_live_poll_cannot_be_initialized_again() {
+        let factory_chain = ChainId(CryptoHash::test_hash("factory"));
+        let runtime = ContractRuntime::new()
+            .with_application_parameters(())
+            .with_message_origin_chain_id(factory_chain);
+        let mut contract = create_poll_on(runtime, 2, PollConfig::default());
+
+        contract.runtime.set_message_origin_chain_id(ChainId(CryptoHash::test_hash("mallory")));
+        let hijack = Message::InitializePoll {
+            topic: "Mine now".to_string(),
+            votes_per_voter: 2,
+            admin_id: "mallory".to_string(),
+            config: Box::new(PollConfig::default()),
+            nominations: Vec::new(),
+        };
+        assert_eq!(
+            contract.try_execute_message(hijack).now_or_never().unwrap(),
+            Err(MealVotingError::AlreadyInitialized)
+        );
+        assert_eq!(contract.state.admin_id.get(), "admin");
+        assert_eq!(*contract.state.creator_chain.get(), Some(factory_chain));
+        assert_eq!(contract.state.topic.get(), "Lunch");
+    }
+
+    fn four_voter_borda(borda_variant: BordaVariant) -> Vec<ResultEntry> {
+        let mut contract = create_poll_with_config(3, PollConfig { borda_variant, ..PollConfig::default() });
+        let voters = ["v1", "v2", "v3", "v4"];
+        for voter in voters {
+            execute(&mut contract, Operation::Join { name: voter.to_string(), owner: voter.to_string() });
+        }
+        for text in ["A", "B", "C"] {
+            execute(&mut contract, Operation::Nominate { text: text.to_string(), category: None, tags: Vec::new(), owner: "admin".to_string() });
+        }
+        execute(&mut contract, Operation::StartVote { owner: "admin".to_string() });
+        let ballots = [[0, 1, 2], [0, 1, 2], [1, 2, 0], [2, 1, 0]];
+        for (voter, ballot) in voters.iter().zip(ballots) {
+            let rankings = ballot.iter().map(|i| format!("nom_{}", i)).collect();
+            execute(&mut contract, Operation::Vote { rankings, owner: voter.to_string() });
+        }
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+        contract.state.results.get().clone()
+    }
+
+    #[test]
+    fn dowdall_weighting_can_change_the_winner() {
+        // B is everyone's first or second choice; A has the most first places.
+        let linear = four_voter_borda(BordaVariant::Linear);
+        assert_eq!((linear[0].nomination_id.as_str(), linear[0].score), ("nom_1", 9));
+
+        let dowdall = four_voter_borda(BordaVariant::Dowdall);
+        assert_eq!((dowdall[0].nomination_id.as_str(), dowdall[0].score), ("nom_0", 6720));
+    }
+
+    #[test]
+    fn top_heavy_points_halve_with_each_place() {
+        let points: Vec<_> = (0..4).map(|position| BordaVariant::TopHeavy.points(3, position)).collect();
+        assert_eq!(points, vec![4, 2, 1, 0]);
+    }
+
+    #[test]
+    fn a_bad_item_rejects_the_whole_nomination_batch() {
+        let mut contract = create_poll_with_config(2, PollConfig { max_nominations_per_user: 3, ..PollConfig::default() });
+        execute(&mut contract, Operation::Join { name: "alice".to_string(), owner: "alice".to_string() });
+        execute(&mut contract, Operation::Nominate { text: "Pizza".to_string(), category: None, tags: Vec::new(), owner: "admin".to_string() });
+        let batch = |texts: &[&str]| Operation::NominateBatch {
+            texts: texts.iter().map(|text| text.to_string()).collect(),
+            owner: "alice".to_string(),
+        };
+
+        assert_eq!(
+            try_execute(&mut contract, batch(&["Tacos", " pizza "])),
+            Err(MealVotingError::DuplicateNomination("nom_0".to_string()))
+        );
+        assert_eq!(
+            try_execute(&mut contract, batch(&["Tacos", "Curry", "Ramen", "Pho"])),
+            Err(MealVotingError::NominationLimitReached(3))
+        );
+        assert_eq!(contract.state.nominations.count().now_or_never().unwrap().unwrap(), 1);
+
+        execute(&mut contract, batch(&["Tacos", "Curry"]));
+        let texts: Vec<_> = ["nom_1", "nom_2"]
+            .iter()
+            .map(|id| contract.state.nominations.get(*id).now_or_never().unwrap().unwrap().unwrap().text)
+            .collect();
+        assert_eq!(texts, vec!["Tacos", "Curry"]);
+    }
+
+    #[test]
+    fn a_delegated_vote_changes_the_outcome() {
+        let winner = |delegate: bool| {
+            let mut contract = create_poll_with_config(2, PollConfig::default());
+            for name in ["alice", "bob", "carol", "dave"] {
+                execute(&mut contract, Operation::Join { name: name.to_string(), owner: name.to_string() });
+            }
+            execute(&mut contract, Operation::Nominate { text: "Pizza".to_string(), category: None, tags: Vec::new(), owner: "alice".to_string() });
+            execute(&mut contract, Operation::Nominate { text: "Sushi".to_string(), category: None, tags: Vec::new(), owner: "bob".to_string() });
+            if delegate {
+                for (from, to) in [("carol", "dave"), ("dave", "bob")] {
+                    execute(&mut contract, Operation::Delegate { to: to.to_string(), owner: from.to_string() });
+                }
+            }
+            execute(&mut contract, Operation::StartVote { owner: "admin".to_string() });
+            execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+            execute(&mut contract, Operation::Vote { rankings: vec!["nom_1".to_string()], owner: "bob".to_string() });
+            execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+            contract.state.results.get()[0].clone()
+        };
+
+        // Alone, the 2-2 tie goes to nom_0; carol and dave both inherit bob's ballot via dave.
+        assert_eq!(winner(false).nomination_id, "nom_0");
+        let delegated = winner(true);
+        assert_eq!((delegated.nomination_id.as_str(), delegated.score), ("nom_1", 6));
+    }
+
+    #[test]
+    fn delegation_cycles_inherit_no_ballot() {
+        let mut contract = create_poll_with_config(2, PollConfig::default());
+        for name in ["alice", "bob", "carol"] {
+            execute(&mut contract, Operation::Join { name: name.to_string(), owner: name.to_string() });
+        }
+        execute(&mut contract, Operation::Nominate { text: "Pizza".to_string(), category: None, tags: Vec::new(), owner: "alice".to_string() });
+        assert_eq!(
+            try_execute(&mut contract, Operation::Delegate { to: "bob".to_string(), owner: "bob".to_string() }),
+            Err(MealVotingError::SelfDelegation)
+        );
+        execute(&mut contract, Operation::Delegate { to: "carol".to_string(), owner: "bob".to_string() });
+        execute(&mut contract, Operation::Delegate { to: "bob".to_string(), owner: "carol".to_string() });
+        execute(&mut contract, Operation::StartVote { owner: "admin".to_string() });
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+
+        let results = contract.state.results.get();
+        assert_eq!((results[0].nomination_id.as_str(), results[0].score), ("nom_0", 2));
+    }
+
+    fn edit(nomination_id: &str, new_text: &str, owner: &str) -> Operation {
+        Operation::EditNomination {
+            nomination_id: nomination_id.to_string(),
+            new_text: new_text.to_string(),
+            owner: owner.to_string(),
+        }
+    }
+
+    #[test]
+    fn nominators_can_edit_their_own_nomination() {
+        let mut contract = create_poll_with_config(2, PollConfig::default());
+        execute(&mut contract, Operation::Join { name: "alice".to_string(), owner: "alice".to_string() });
+        execute(&mut contract, Operation::Nominate { text: "Piza".to_string(), category: None, tags: Vec::new(), owner: "alice".to_string() });
+        execute(&mut contract, Operation::Nominate { text: "Sushi".to_string(), category: None, tags: Vec::new(), owner: "admin".to_string() });
+
+        assert_eq!(
+            try_execute(&mut contract, edit("nom_0", "sushi", "alice")),
+            Err(MealVotingError::DuplicateNomination("nom_1".to_string()))
+        );
+        execute(&mut contract, edit("nom_0", "Pizza", "alice"));
+
+        let nomination = contract.state.nominations.get("nom_0").now_or_never().unwrap().unwrap().unwrap();
+        assert_eq!(nomination.text, "Pizza");
+    }
+
+    #[test]
+    fn editing_someone_elses_nomination_is_rejected() {
+        let mut contract = voting_poll(2);
+        execute(&mut contract, Operation::ReopenNominations { owner: "admin".to_string() });
+        assert_eq!(
+            try_execute(&mut contract, edit("nom_0", "Calzone", "bob")),
+            Err(MealVotingError::NotNominator("edit the nomination"))
+        );
+    }
+
+    #[test]
+    fn nominations_cannot_be_edited_after_voting_starts() {
+        let mut contract = voting_poll(2);
+        assert_eq!(
+            try_execute(&mut contract, edit("nom_0", "Calzone", "alice")),
+            Err(MealVotingError::NominatingClosed)
+        );
+    }
+
+    #[test]
+    fn a_configured_tie_break_seed_orders_ties_reproducibly() {
+        let tie_order = |seed| {
+            let mut contract = voting_poll_with_config(2, PollConfig { tie_break_seed: Some(seed), ..PollConfig::default() });
+            execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+            execute(&mut contract, Operation::Vote { rankings: vec!["nom_1".to_string()], owner: "bob".to_string() });
+            execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+            contract.state.results.get().iter().map(|entry| entry.nomination_id.clone()).collect::<Vec<_>>()
+        };
+
+        for seed in [1, 7, 42] {
+            let order = tie_order(seed);
+            assert_eq!(order, tie_order(seed));
+            let mut expected = vec!["nom_0".to_string(), "nom_1".to_string()];
+            expected.sort_by_key(|id| tie_break_hash(seed, id));
+            assert_eq!(order, expected);
+        }
+    }
+
+    #[test]
+    fn write_ins_become_nominations_only_when_allowed() {
+        let write_in = || Operation::Vote { rankings: vec!["Tacos".to_string(), "nom_0".to_string()], owner: "alice".to_string() };
+
+        let mut closed = voting_poll(2);
+        assert_eq!(try_execute(&mut closed, write_in()), Err(MealVotingError::UnknownNomination("Tacos".to_string())));
+
+        let mut open = voting_poll_with_config(2, PollConfig { allow_write_ins: true, ..PollConfig::default() });
+        execute(&mut open, write_in());
+        let ballot = open.state.rankings.get("alice").now_or_never().unwrap().unwrap().unwrap();
+        assert_eq!(ballot, vec!["nom_2".to_string(), "nom_0".to_string()]);
+        let nomination = open.state.nominations.get("nom_2").now_or_never().unwrap().unwrap().unwrap();
+        assert_eq!((nomination.text.as_str(), nomination.user_id.as_str()), ("Tacos", "alice"));
+
+        // A later write-in of the same name reuses the nomination.
+        execute(&mut open, Operation::Vote { rankings: vec!["tacos".to_string()], owner: "bob".to_string() });
+        let ballot = open.state.rankings.get("bob").now_or_never().unwrap().unwrap().unwrap();
+        assert_eq!(ballot, vec!["nom_2".to_string()]);
+    }
+
+    #[test]
+    fn closing_pushes_results_to_the_creating_chain() {
+        let factory_chain = ChainId(CryptoHash::test_hash("factory"));
+        let poll_chain = ChainId(CryptoHash::test_hash("poll"));
+
+        let runtime = ContractRuntime::new()
+            .with_application_parameters(())
+            .with_message_origin_chain_id(factory_chain);
+        let mut poll = create_poll_on(runtime, 2, PollConfig::default());
+        execute(&mut poll, Operation::Join { name: "alice".to_string(), owner: "alice".to_string() });
+        execute(&mut poll, Operation::Nominate { text: "Pizza".to_string(), category: None, tags: Vec::new(), owner: "alice".to_string() });
+        execute(&mut poll, Operation::StartVote { owner: "admin".to_string() });
+        execute(&mut poll, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+        execute(&mut poll, Operation::ClosePoll { owner: "admin".to_string() });
+        let report = poll.runtime.created_send_message_requests().pop().unwrap();
+        assert_eq!(report.destination, factory_chain);
+
+        let runtime = ContractRuntime::new().with_application_parameters(()).with_message_origin_chain_id(poll_chain);
+        let mut factory = MealVotingContract {
+            state: PollState::load(runtime.root_view_storage_context()).blocking_wait().unwrap(),
+            runtime,
+        };
+        factory.execute_message(report.message).now_or_never().unwrap();
+
+        let stored = factory.state.poll_results.get(&poll_chain).now_or_never().unwrap().unwrap().unwrap();
+        assert_eq!(stored, *poll.state.results.get());
+        assert_eq!(stored[0].nomination_text, "Pizza");
+    }
+
+    #[test]
+    fn start_vote_requires_a_nomination() {
+        let mut contract = create_poll_with_config(2, PollConfig::default());
+        execute(&mut contract, Operation::Join { name: "alice".to_string(), owner: "alice".to_string() });
+
+        assert_eq!(
+            try_execute(&mut contract, Operation::StartVote { owner: "admin".to_string() }),
+            Err(MealVotingError::EmptyPoll)
+        );
+        assert!(!*contract.state.has_started.get());
+
+        execute(&mut contract, Operation::Nominate { text: "Pizza".to_string(), category: None, tags: Vec::new(), owner: "alice".to_string() });
+        execute(&mut contract, Operation::StartVote { owner: "admin".to_string() });
+        assert!(*contract.state.has_started.get());
+    }
+
+    #[test]
+    fn retracting_a_vote_removes_the_ballot() {
+        let mut contract = create_poll_with_config(2, PollConfig::default());
+        for user in ["alice", "bob"] {
+            execute(&mut contract, Operation::Join { name: user.to_string(), owner: user.to_string() });
+        }
+        execute(&mut contract, Operation::Nominate { text: "Pizza".to_string(), category: None, tags: Vec::new(), owner: "alice".to_string() });
+        assert_eq!(
+            try_execute(&mut contract, Operation::RetractVote { owner: "alice".to_string() }),
+            Err(MealVotingError::VotingNotStarted)
+        );
+        execute(&mut contract, Operation::StartVote { owner: "admin".to_string() });
+        execute(&mut contract, Operation::Vote { rankings: vec!["nom_0".to_string()], owner: "alice".to_string() });
+        execute(&mut contract, Operation::Vote { rankings: Vec::new(), owner: "bob".to_string() });
+        assert_eq!(contract.state.rankings.count().now_or_never().unwrap().unwrap(), 2);
+
+        execute(&mut contract, Operation::RetractVote { owner: "alice".to_string() });
+        execute(&mut contract, Operation::RetractVote { owner: "bob".to_string() });
+
+        assert_eq!(contract.state.rankings.count().now_or_never().unwrap().unwrap(), 0);
+        assert_eq!(contract.state.ranking_times.get("alice").now_or_never().unwrap().unwrap(), None);
+        assert_eq!(*contract.state.abstentions.get(), 0);
+        assert_eq!(
+            try_execute(&mut contract, Operation::RetractVote { owner: "alice".to_string() }),
+            Err(MealVotingError::NoBallot)
+        );
+
+        execute(&mut contract, Operation::ClosePoll { owner: "admin".to_string() });
+        assert!(contract.state.results.get().is_empty());
+    }
+
+    #[test]
+    fn each_poll_draws_its_own_ballot_salt() {
+        let first = create_poll_with_config(2, PollConfig { anonymous_ballots: true, ..PollConfig::default() });
+        let runtime = ContractRuntime::new().with_application_parameters(()).with_system_time(Timestamp::from(1));
+        let second = create_poll_on(runtime, 2, PollConfig { anonymous_ballots: true, ..PollConfig::default() });
+
+        assert_eq!(first.state.ballot_salt.get().len(), 64);
+        assert_ne!(first.state.ballot_salt.get(), second.state.ballot_salt.get());
     }
 }
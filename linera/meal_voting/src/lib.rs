@@ -3,42 +3,504 @@
 
 /*! ABI of the Meal Voting Application */
 
-use async_graphql::{Request, Response};
-use linera_sdk::linera_base_types::{ContractAbi, ServiceAbi};
+use async_graphql::{Enum, InputObject, Request, Response, SimpleObject};
+use linera_sdk::linera_base_types::{Account, Amount, ChainId, ContractAbi, ServiceAbi, Timestamp};
 use serde::{Deserialize, Serialize};
 
 pub struct MealVotingAbi;
 
+/// Nomination ID of the automatic "None of the above" option.
+pub const NONE_OF_THE_ABOVE_ID: &str = "nota";
+
 /// Operations that can be executed on the contract.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum Operation {
     /// Initialize a new poll with a topic and votes per voter.
-    CreatePoll { topic: String, votes_per_voter: u32, owner: String },
-    /// Join the poll as a participant.
+    CreatePoll { topic: String, votes_per_voter: u32, owner: String, config: PollConfig },
+    /// Join the poll as a participant. Joining again keeps the current name; use `Rename`.
     Join { name: String, owner: String },
-    /// Add a nomination to the poll (local chain only).
-    Nominate { text: String, owner: String },
-    /// Submit rankings for the nominations (local chain only).
+    /// Add a nomination to the poll (local chain only), optionally in a budgeted category and
+    /// with dietary tags such as "vegan".
+    Nominate { text: String, category: Option<String>, tags: Vec<String>, owner: String },
+    /// Submit rankings for the nominations (local chain only). Empty rankings record an abstention.
     Vote { rankings: Vec<String>, owner: String },
+    /// Distribute the voter's point budget across nominations (cumulative voting).
+    VotePoints { allocations: Vec<(String, u64)>, owner: String },
+    /// Rate nominations with 1 to 5 stars each (score voting).
+    RateOptions { ratings: Vec<(String, u8)>, owner: String },
+    /// Publicly endorse a nomination (display only, doesn't affect tallies).
+    EndorseNomination { nomination_id: String, owner: String },
+    /// Comment on a nomination (participants only).
+    CommentOnNomination { nomination_id: String, text: String, owner: String },
     /// Start the voting phase (admin only).
     StartVote { owner: String },
     /// Close the poll and compute results (admin only).
     ClosePoll { owner: String },
+    /// Reopen a closed poll for voting, discarding its results (admin only, until finalized).
+    ReopenPoll { owner: String },
+    /// Freeze a closed poll's results for good; every later operation is rejected (admin only).
+    FinalizeResults { owner: String },
+    /// Change the number of votes each participant can cast (admin only).
+    SetVotesPerVoter { votes_per_voter: u32, owner: String },
+    /// Re-draw the tie-break seed and re-resolve tied results (admin only, after close).
+    RerollTieBreak { owner: String },
+    /// Register the account that receives the payout if this nomination wins (nominator only).
+    SetPayoutAddress { nomination_id: String, address: Account, owner: String },
+    /// Transfer the chain's remaining balance to the winning nomination's payout address (admin only).
+    PayoutWinner { owner: String },
+    /// Open a new poll chain seeded with another poll's topic and nominations (factory chain).
+    ClonePoll { source_chain: ChainId, owner: String },
+    /// Ask poll chains for their current results, to aggregate them here (factory chain).
+    CollectResults { chain_ids: Vec<ChainId>, owner: String },
+    /// Toggle whether participants may still join after voting has started (admin only).
+    AllowLateJoin { owner: String },
+    /// Restrict voting to a subset of the nominations; an empty list lifts the restriction (admin only).
+    SetShortlist { nomination_ids: Vec<String>, owner: String },
+    /// Give a nomination a head start that is added to its tally (admin only).
+    SetBaseScore { nomination_id: String, base_score: u64, owner: String },
+    /// Set a participant's voting weight, which multiplies their ranked-ballot points and counts
+    /// toward the weighted quorum (admin only).
+    SetParticipantWeight { user_id: String, weight: u64, owner: String },
+    /// Temporarily freeze all non-admin activity (admin only).
+    PausePoll { owner: String },
+    /// Lift a pause (admin only).
+    ResumePoll { owner: String },
+    /// Remove a participant along with their nominations and ballots, before voting starts
+    /// (admin only).
+    RemoveParticipant { target: String, owner: String },
+    /// Return an open poll from voting to the nomination phase, discarding every ballot cast so
+    /// far (admin only).
+    ReopenNominations { owner: String },
+    /// Hand the admin role to another participant (admin only).
+    TransferAdmin { new_admin: String, owner: String },
+    /// Change the caller's display name (participants only).
+    Rename { name: String, owner: String },
+    /// Abort the poll without computing results; it accepts no more operations (admin only).
+    CancelPoll { owner: String },
+    /// Send this closed shard's results to the coordinator chain merging them (admin only).
+    SubmitPartialResults { coordinator: ChainId, owner: String },
+    /// Accept partial results from this shard chain on this coordinator (admin only).
+    RegisterShard { shard: ChainId, owner: String },
+    /// Add several nominations at once; if any of them is refused, none are added.
+    NominateBatch { texts: Vec<String>, owner: String },
+    /// Let another participant vote for you: if you cast no ballot, theirs counts for you too
+    /// (participants only).
+    Delegate { to: String, owner: String },
+    /// Reword your own nomination before voting starts (nominator only).
+    EditNomination { nomination_id: String, new_text: String, owner: String },
+    /// Withdraw your ranked ballot entirely while the poll is open, unlike abstaining which
+    /// leaves an empty one.
+    RetractVote { owner: String },
+}
+
+impl Operation {
+    /// The user ID on whose behalf the operation is submitted.
+    pub fn owner(&self) -> &str {
+        match self {
+            Operation::CreatePoll { owner, .. }
+            | Operation::Join { owner, .. }
+            | Operation::Nominate { owner, .. }
+            | Operation::Vote { owner, .. }
+            | Operation::VotePoints { owner, .. }
+            | Operation::RateOptions { owner, .. }
+            | Operation::EndorseNomination { owner, .. }
+            | Operation::CommentOnNomination { owner, .. }
+            | Operation::StartVote { owner }
+            | Operation::ClosePoll { owner }
+            | Operation::ReopenPoll { owner }
+            | Operation::FinalizeResults { owner }
+            | Operation::SetVotesPerVoter { owner, .. }
+            | Operation::RerollTieBreak { owner }
+            | Operation::SetPayoutAddress { owner, .. }
+            | Operation::PayoutWinner { owner }
+            | Operation::ClonePoll { owner, .. }
+            | Operation::CollectResults { owner, .. }
+            | Operation::AllowLateJoin { owner }
+            | Operation::SetShortlist { owner, .. }
+            | Operation::SetBaseScore { owner, .. }
+            | Operation::SetParticipantWeight { owner, .. }
+            | Operation::PausePoll { owner }
+            | Operation::ResumePoll { owner }
+            | Operation::RemoveParticipant { owner, .. }
+            | Operation::ReopenNominations { owner }
+            | Operation::TransferAdmin { owner, .. }
+            | Operation::Rename { owner, .. }
+            | Operation::CancelPoll { owner }
+            | Operation::SubmitPartialResults { owner, .. }
+            | Operation::RegisterShard { owner, .. }
+            | Operation::NominateBatch { owner, .. }
+            | Operation::Delegate { owner, .. }
+            | Operation::EditNomination { owner, .. }
+            | Operation::RetractVote { owner } => owner,
+        }
+    }
+
+    /// The variant name, e.g. `"Vote"`, used to label audit log entries.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Operation::CreatePoll { .. } => "CreatePoll",
+            Operation::Join { .. } => "Join",
+            Operation::Nominate { .. } => "Nominate",
+            Operation::Vote { .. } => "Vote",
+            Operation::VotePoints { .. } => "VotePoints",
+            Operation::RateOptions { .. } => "RateOptions",
+            Operation::EndorseNomination { .. } => "EndorseNomination",
+            Operation::CommentOnNomination { .. } => "CommentOnNomination",
+            Operation::StartVote { .. } => "StartVote",
+            Operation::ClosePoll { .. } => "ClosePoll",
+            Operation::ReopenPoll { .. } => "ReopenPoll",
+            Operation::FinalizeResults { .. } => "FinalizeResults",
+            Operation::SetVotesPerVoter { .. } => "SetVotesPerVoter",
+            Operation::RerollTieBreak { .. } => "RerollTieBreak",
+            Operation::SetPayoutAddress { .. } => "SetPayoutAddress",
+            Operation::PayoutWinner { .. } => "PayoutWinner",
+            Operation::ClonePoll { .. } => "ClonePoll",
+            Operation::CollectResults { .. } => "CollectResults",
+            Operation::AllowLateJoin { .. } => "AllowLateJoin",
+            Operation::SetShortlist { .. } => "SetShortlist",
+            Operation::SetBaseScore { .. } => "SetBaseScore",
+            Operation::SetParticipantWeight { .. } => "SetParticipantWeight",
+            Operation::PausePoll { .. } => "PausePoll",
+            Operation::ResumePoll { .. } => "ResumePoll",
+            Operation::RemoveParticipant { .. } => "RemoveParticipant",
+            Operation::ReopenNominations { .. } => "ReopenNominations",
+            Operation::TransferAdmin { .. } => "TransferAdmin",
+            Operation::Rename { .. } => "Rename",
+            Operation::CancelPoll { .. } => "CancelPoll",
+            Operation::SubmitPartialResults { .. } => "SubmitPartialResults",
+            Operation::RegisterShard { .. } => "RegisterShard",
+            Operation::NominateBatch { .. } => "NominateBatch",
+            Operation::Delegate { .. } => "Delegate",
+            Operation::EditNomination { .. } => "EditNomination",
+            Operation::RetractVote { .. } => "RetractVote",
+        }
+    }
+}
+
+/// Optional poll settings chosen at creation time.
+#[derive(Debug, Clone, Deserialize, Serialize, InputObject)]
+pub struct PollConfig {
+    /// Maximum points a voter may put on a single nomination in cumulative voting.
+    #[serde(default)]
+    #[graphql(default)]
+    pub max_points_per_option: Option<u64>,
+    /// What happens to existing ballots when `votes_per_voter` is lowered below their length.
+    #[serde(default)]
+    #[graphql(default)]
+    pub ballot_limit_policy: BallotLimitPolicy,
+    /// Reject a second ballot from the same voter instead of overwriting the first.
+    #[serde(default)]
+    #[graphql(default)]
+    pub ballots_final: bool,
+    /// Reject joining, nominating and voting before this time.
+    #[serde(default)]
+    #[graphql(default)]
+    pub opens_at: Option<Timestamp>,
+    /// Assumed cost of one operation, used only for the `estimated_fees` query.
+    #[serde(default)]
+    #[graphql(default)]
+    pub fee_per_operation: Amount,
+    /// Total weight of voters required before the poll can be closed (`None` = no quorum).
+    #[serde(default)]
+    #[graphql(default)]
+    pub quorum_weight: Option<u64>,
+    /// Let voters rank nominations they submitted themselves.
+    #[serde(default = "default_true")]
+    #[graphql(default = true)]
+    pub allow_self_ranking: bool,
+    /// Add a rankable "None of the above" nomination when the poll is created.
+    #[serde(default)]
+    #[graphql(default)]
+    pub none_of_the_above: bool,
+    /// Per-category pick allowances; ballots may rank at most `picks` nominations of each category.
+    #[serde(default)]
+    #[graphql(default)]
+    pub category_budgets: Vec<CategoryBudget>,
+    /// When the decision must be acted on (e.g. booking a table); display only, gates nothing.
+    #[serde(default)]
+    #[graphql(default)]
+    pub decision_deadline: Option<Timestamp>,
+    /// How a nomination whose text matches an existing one is handled.
+    #[serde(default)]
+    #[graphql(default)]
+    pub dedup_strategy: DedupStrategy,
+    /// Reject ballots that leave out any active nomination (up to `votes_per_voter` of them).
+    #[serde(default)]
+    #[graphql(default)]
+    pub require_full_ranking: bool,
+    /// Close the poll as soon as any nomination's provisional score reaches this ("first to X").
+    #[serde(default)]
+    #[graphql(default)]
+    pub winning_score_threshold: Option<u64>,
+    /// Keep invalid ballots in `rejected_ballots` with the reason instead of failing the vote.
+    #[serde(default)]
+    #[graphql(default)]
+    pub record_rejected_ballots: bool,
+    /// How ranked ballots are turned into results when the poll closes.
+    #[serde(default)]
+    #[graphql(default)]
+    pub tally_method: TallyMethod,
+    /// Close the poll automatically once the chain's time passes this, in microseconds.
+    #[serde(default)]
+    #[graphql(default)]
+    pub deadline_micros: Option<u64>,
+    /// Most participants the poll admits, the admin included (`None` = unlimited).
+    #[serde(default)]
+    #[graphql(default)]
+    pub max_participants: Option<u32>,
+    /// Ranked ballots needed for the results to count as decisive; closing still works below it,
+    /// but `quorum_met` stays false (0 = no quorum).
+    #[serde(default)]
+    #[graphql(default)]
+    pub quorum: u32,
+    /// Longer context shown alongside the topic.
+    #[serde(default)]
+    #[graphql(default)]
+    pub description: String,
+    /// Free-form label for grouping polls, e.g. "Team lunch".
+    #[serde(default)]
+    #[graphql(default)]
+    pub category: String,
+    /// Fewest nominations a non-empty ranked ballot must rank (0 = no minimum).
+    #[serde(default)]
+    #[graphql(default)]
+    pub min_rankings: u32,
+    /// Most nominations each participant may make (0 = unlimited).
+    #[serde(default)]
+    #[graphql(default)]
+    pub max_nominations_per_user: u32,
+    /// Seats filled under `TallyMethod::Stv` (0 = one).
+    #[serde(default)]
+    #[graphql(default)]
+    pub multi_winner: u32,
+    /// Hide ballots and results from queries until the poll closes.
+    #[serde(default)]
+    #[graphql(default)]
+    pub hide_ballots_until_close: bool,
+    /// Point curve for ranked ballots under `TallyMethod::Borda`.
+    #[serde(default)]
+    #[graphql(default)]
+    pub borda_variant: BordaVariant,
+    /// Seed ordering tied results by a hash of `(seed, nomination_id)`; `None` orders them by ID.
+    #[serde(default)]
+    #[graphql(default)]
+    pub tie_break_seed: Option<u64>,
+    /// Let voters rank new options by name; each becomes a nomination by that voter, so this
+    /// requires `allow_self_ranking`.
+    #[serde(default)]
+    #[graphql(default)]
+    pub allow_write_ins: bool,
+    /// Show ballots under a salted hash of each voter instead of their user ID.
+    #[serde(default)]
+    #[graphql(default)]
+    pub anonymous_ballots: bool,
+}
+
+impl Default for PollConfig {
+    fn default() -> Self {
+        PollConfig {
+            max_points_per_option: None,
+            ballot_limit_policy: BallotLimitPolicy::default(),
+            ballots_final: false,
+            opens_at: None,
+            fee_per_operation: Amount::ZERO,
+            quorum_weight: None,
+            allow_self_ranking: true,
+            none_of_the_above: false,
+            category_budgets: Vec::new(),
+            decision_deadline: None,
+            dedup_strategy: DedupStrategy::default(),
+            require_full_ranking: false,
+            winning_score_threshold: None,
+            record_rejected_ballots: false,
+            tally_method: TallyMethod::default(),
+            deadline_micros: None,
+            max_participants: None,
+            quorum: 0,
+            description: String::new(),
+            category: String::new(),
+            min_rankings: 0,
+            max_nominations_per_user: 0,
+            multi_winner: 0,
+            hide_ballots_until_close: false,
+            borda_variant: BordaVariant::default(),
+            tie_break_seed: None,
+            allow_write_ins: false,
+            anonymous_ballots: false,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// What to do with a nomination whose text matches an existing one (ignoring case and
+/// surrounding whitespace).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, Enum)]
+pub enum DedupStrategy {
+    /// Refuse the duplicate.
+    #[default]
+    Reject,
+    /// Drop the duplicate so every vote goes to the existing nomination, and count its nominator
+    /// as an endorser of that nomination.
+    Merge,
+    /// Keep both nominations.
+    Allow,
+}
+
+/// How closing results are computed from the ballots.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, Enum)]
+pub enum TallyMethod {
+    /// Positional points from ranked ballots, plus point allocations, star ratings and base scores.
+    #[default]
+    Borda,
+    /// Ranked-choice: repeatedly eliminate the nomination with the fewest first choices and
+    /// transfer its ballots until one nomination holds a majority.
+    InstantRunoff,
+    /// One vote per ranked ballot, for its first choice.
+    Plurality,
+    /// Ballots are unordered sets of approved nominations; each approval is worth one point.
+    Approval,
+    /// Single transferable vote filling `multi_winner` seats against a Droop quota; surplus
+    /// votes of elected nominations transfer at a fractional value.
+    Stv,
 }
 
+/// How Borda points fall off with a nomination's position on a ranked ballot.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, Enum)]
+pub enum BordaVariant {
+    /// `votes_per_voter` points for first place, one less for each place after.
+    #[default]
+    Linear,
+    /// 1, 1/2, 1/3, ... of `DOWDALL_SCALE` points.
+    Dowdall,
+    /// Points halve with each place: 2^(votes_per_voter - 1) for first place, 1 for the last.
+    TopHeavy,
+}
+
+/// Points for first place under `BordaVariant::Dowdall`; divisible by every place up to 10.
+pub const DOWDALL_SCALE: u64 = 2520;
+
+impl BordaVariant {
+    /// Points for ranking a nomination at `position` (from 0) on a ballot of up to `max_votes`.
+    pub fn points(&self, max_votes: u64, position: usize) -> u64 {
+        let position = position as u64;
+        if position >= max_votes {
+            return 0;
+        }
+        match self {
+            BordaVariant::Linear => max_votes - position,
+            BordaVariant::Dowdall => DOWDALL_SCALE / (position + 1),
+            BordaVariant::TopHeavy => 1u64.checked_shl((max_votes - 1 - position) as u32).unwrap_or(u64::MAX),
+        }
+    }
+}
+
+/// How many nominations of one category a ballot may rank.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, SimpleObject, InputObject)]
+#[graphql(input_name = "CategoryBudgetInput")]
+pub struct CategoryBudget {
+    pub category: String,
+    pub picks: u32,
+}
+
+/// How ballots cast under a larger `votes_per_voter` are reconciled when the limit is lowered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, Enum)]
+pub enum BallotLimitPolicy {
+    /// Refuse the change while any ballot exceeds the new limit.
+    #[default]
+    Reject,
+    /// Truncate over-long ballots to the new limit.
+    Truncate,
+}
+
+/// A computed result entry.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, SimpleObject)]
+pub struct ResultEntry {
+    pub nomination_id: String,
+    pub nomination_text: String,
+    pub score: u64,
+    /// Instant-runoff or STV round in which the nomination was eliminated; `None` if it never was.
+    pub eliminated_in_round: Option<u32>,
+    /// Ranked ballots placing the nomination first.
+    pub first_choice_votes: u64,
+    /// Whether the nomination won one of the seats of an STV count.
+    pub elected: bool,
+}
+
+/// Events emitted on the poll chain's `POLL_EVENTS_STREAM` for indexers.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum PollEvent {
+    /// The poll chain was initialized.
+    PollCreated { topic: String, admin_id: String },
+    /// A new participant joined.
+    ParticipantJoined { user_id: String },
+    /// An existing participant sent `Join` again; their name is unchanged.
+    ParticipantRejoined { user_id: String },
+    /// A participant changed their display name.
+    ParticipantRenamed { user_id: String, name: String },
+    /// The poll moved from nominating to voting.
+    VotingStarted,
+    /// The poll closed; followed by `ResultsComputed`.
+    PollClosed,
+    /// Results were computed or re-resolved; carries the full sorted tally.
+    ResultsComputed { results: Vec<ResultEntry> },
+}
+
+/// Name of the event stream carrying `PollEvent`s.
+pub const POLL_EVENTS_STREAM: &[u8] = b"poll_events";
+
 /// Cross-chain messages for remote poll participation.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum Message {
     /// Initialize a new poll (sent to new chain).
-    InitializePoll { topic: String, votes_per_voter: u32, admin_id: String },
+    /// `nominations` are `(user_id, text)` pairs to pre-populate, e.g. when cloning a poll.
+    InitializePoll {
+        topic: String,
+        votes_per_voter: u32,
+        admin_id: String,
+        config: Box<PollConfig>,
+        nominations: Vec<(String, String)>,
+    },
     /// Nominate on a poll from another chain.
-    Nominate { user_id: String, text: String },
+    Nominate { user_id: String, text: String, category: Option<String>, tags: Vec<String> },
+    /// Nominate several options at once from another chain, all or nothing.
+    NominateBatch { user_id: String, texts: Vec<String> },
     /// Vote on a poll from another chain.
     Vote { user_id: String, rankings: Vec<String> },
     /// Start voting phase (cross-chain, admin only).
     StartVote { user_id: String },
     /// Close poll (cross-chain, admin only).
     ClosePoll { user_id: String },
+    /// Remove a participant before voting starts (cross-chain, admin only).
+    RemoveParticipant { user_id: String, target: String },
+    /// Return to the nomination phase, discarding ballots (cross-chain, admin only).
+    ReopenNominations { user_id: String },
+    /// Hand the admin role to another participant (cross-chain, admin only).
+    TransferAdmin { user_id: String, new_admin: String },
+    /// Abort the poll without results (cross-chain, admin only).
+    CancelPoll { user_id: String },
+    /// Ask a poll chain for the data needed to clone it (sent by the factory chain).
+    RequestClone { owner: String },
+    /// A poll's topic, settings and nominations, sent back to the factory chain that requested a
+    /// clone.
+    CloneData {
+        topic: String,
+        votes_per_voter: u32,
+        config: Box<PollConfig>,
+        nominations: Vec<(String, String)>,
+        owner: String,
+    },
+    /// Ask a poll chain to report its results back to the sender.
+    RequestResults,
+    /// A poll chain's current results, sent back to the chain that requested them, or pushed to
+    /// the chain that created the poll when it closes.
+    ReportResults { results: Vec<ResultEntry> },
+    /// A user joined a poll chain; sent to the chain that created the poll.
+    JoinedPoll { user_id: String },
+    /// A shard chain's results, merged into the coordinator chain's own results.
+    SubmitPartialResults { scores: Vec<ResultEntry> },
 }
 
 impl ContractAbi for MealVotingAbi {
@@ -5,18 +5,25 @@
 
 mod state;
 
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
 
-use async_graphql::{EmptySubscription, Object, Request, Response, Schema};
+use async_graphql::{EmptySubscription, Error, InputObject, Object, Request, Response, Schema};
 use linera_sdk::{
-    linera_base_types::{ChainId, WithServiceAbi},
+    linera_base_types::{Account, Amount, ChainId, Timestamp, WithServiceAbi},
     views::View,
     Service, ServiceRuntime,
 };
-use meal_voting::Operation;
+use meal_voting::{BallotLimitPolicy, BordaVariant, DedupStrategy, Operation, PollConfig, TallyMethod};
+use sha2::{Digest, Sha256};
 
 use self::state::PollState;
 
+/// Largest nomination count for which `kemeny_ranking` searches every ordering (8! = 40320).
+const KEMENY_MAX_NOMINATIONS: usize = 8;
+
 pub struct MealVotingService {
     state: Arc<PollState>,
     runtime: Arc<ServiceRuntime<Self>>,
@@ -46,6 +53,7 @@ impl Service for MealVotingService {
         let schema = Schema::build(
             QueryRoot {
                 state: self.state.clone(),
+                runtime: self.runtime.clone(),
             },
             MutationRoot {
                 runtime: self.runtime.clone(),
@@ -59,6 +67,7 @@ impl Service for MealVotingService {
 
 struct QueryRoot {
     state: Arc<PollState>,
+    runtime: Arc<ServiceRuntime<MealVotingService>>,
 }
 
 #[Object]
@@ -68,6 +77,88 @@ impl QueryRoot {
         self.state.topic.get().clone()
     }
 
+    /// Get the fewest nominations a non-empty ranked ballot must rank (0 = no minimum).
+    async fn min_rankings(&self) -> u32 {
+        *self.state.min_rankings.get()
+    }
+
+    /// Get the number of seats filled under STV (0 = one).
+    async fn multi_winner(&self) -> u32 {
+        *self.state.multi_winner.get()
+    }
+
+    /// Check if ballots may name new options as write-ins.
+    async fn allow_write_ins(&self) -> bool {
+        *self.state.allow_write_ins.get()
+    }
+
+    /// Check if ballots are listed under a salted hash of the voter rather than their ID.
+    async fn anonymous_ballots(&self) -> bool {
+        *self.state.anonymous_ballots.get()
+    }
+
+    /// Get the seed ordering tied results, for auditing a tie-break (`None` orders ties by ID).
+    async fn tie_break_seed(&self) -> Option<u64> {
+        *self.state.tie_break_seed.get()
+    }
+
+    /// Get the point curve applied to ranked ballots under Borda.
+    async fn borda_variant(&self) -> BordaVariant {
+        *self.state.borda_variant.get()
+    }
+
+    /// Check if ballots and results are hidden until the poll closes.
+    async fn hide_ballots_until_close(&self) -> bool {
+        *self.state.hide_ballots_until_close.get()
+    }
+
+    /// Get the most nominations each participant may make (0 = unlimited).
+    async fn max_nominations_per_user(&self) -> u32 {
+        *self.state.max_nominations_per_user.get()
+    }
+
+    /// Get the poll description (empty if none).
+    async fn description(&self) -> String {
+        self.state.description.get().clone()
+    }
+
+    /// Get the poll category (empty if none).
+    async fn category(&self) -> String {
+        self.state.category.get().clone()
+    }
+
+    /// Get the poll's settings and flags in one read, without loading nominations or ballots.
+    async fn meta(&self) -> state::PollMeta {
+        state::PollMeta {
+            topic: self.state.topic.get().clone(),
+            admin_id: self.state.admin_id.get().clone(),
+            votes_per_voter: *self.state.votes_per_voter.get(),
+            has_started: *self.state.has_started.get(),
+            is_closed: *self.state.is_closed.get(),
+            paused: *self.state.paused.get(),
+            opens_at: *self.state.opens_at.get(),
+            closed_at: *self.state.closed_at.get(),
+            max_points_per_option: *self.state.max_points_per_option.get(),
+            ballot_limit_policy: *self.state.ballot_limit_policy.get(),
+            ballots_final: *self.state.ballots_final.get(),
+            allow_self_ranking: *self.state.allow_self_ranking.get(),
+            quorum_weight: *self.state.quorum_weight.get(),
+            fee_per_operation: *self.state.fee_per_operation.get(),
+        }
+    }
+
+    /// Get the app version with the poll's tally method, phase and headcounts, to check a
+    /// deployed chain at a glance.
+    async fn health(&self) -> state::PollHealth {
+        state::PollHealth {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            tally_method: *self.state.tally_method.get(),
+            phase: self.state.phase(),
+            participant_count: self.state.participants.count().await.expect("count failed") as u32,
+            nomination_count: self.state.nominations.count().await.expect("count failed") as u32,
+        }
+    }
+
     /// Get the admin ID.
     async fn admin_id(&self) -> String {
         self.state.admin_id.get().clone()
@@ -78,132 +169,2311 @@ impl QueryRoot {
         *self.state.votes_per_voter.get()
     }
 
+    /// Get the per-nomination cap on cumulative-voting points, if any.
+    async fn max_points_per_option(&self) -> Option<u64> {
+        *self.state.max_points_per_option.get()
+    }
+
+    /// Get how ballots are reconciled when votes per voter is lowered.
+    async fn ballot_limit_policy(&self) -> BallotLimitPolicy {
+        *self.state.ballot_limit_policy.get()
+    }
+
+    /// Check if ballots are final once cast.
+    async fn ballots_final(&self) -> bool {
+        *self.state.ballots_final.get()
+    }
+
+    /// Get how duplicate nominations are handled.
+    async fn dedup_strategy(&self) -> DedupStrategy {
+        *self.state.dedup_strategy.get()
+    }
+
+    /// Get the score at which the poll closes automatically, if any.
+    async fn winning_score_threshold(&self) -> Option<u64> {
+        *self.state.winning_score_threshold.get()
+    }
+
+    /// Check if voters may rank their own nominations.
+    async fn allow_self_ranking(&self) -> bool {
+        *self.state.allow_self_ranking.get()
+    }
+
+    /// Check if participants may join after voting has started.
+    async fn late_join_allowed(&self) -> bool {
+        *self.state.late_join_allowed.get()
+    }
+
     /// Check if voting has started.
     async fn has_started(&self) -> bool {
         *self.state.has_started.get()
     }
 
+    /// Get the number of ranked ballots needed for the results to be decisive.
+    async fn quorum(&self) -> u32 {
+        *self.state.quorum.get()
+    }
+
+    /// Check if the quorum was met when the poll closed (false while open).
+    async fn quorum_met(&self) -> bool {
+        *self.state.quorum_met.get()
+    }
+
+    /// Check if the results are finalized.
+    async fn finalized(&self) -> bool {
+        *self.state.finalized.get()
+    }
+
     /// Check if poll is closed.
     async fn is_closed(&self) -> bool {
         *self.state.is_closed.get()
     }
 
+    /// Check if the admin cancelled the poll.
+    async fn is_cancelled(&self) -> bool {
+        *self.state.is_cancelled.get()
+    }
+
+    /// Get the poll's lifecycle status.
+    async fn status(&self) -> state::PollStatus {
+        self.state.status(self.runtime.system_time())
+    }
+
+    /// Get the poll's lifecycle phase: nominating, voting or closed.
+    async fn phase(&self) -> state::Phase {
+        self.state.phase()
+    }
+
+    /// Get the time the poll opens for participation, if scheduled.
+    async fn opens_at(&self) -> Option<Timestamp> {
+        *self.state.opens_at.get()
+    }
+
+    /// Get the time by which the decision must be acted on, if set.
+    async fn decision_deadline(&self) -> Option<Timestamp> {
+        *self.state.decision_deadline.get()
+    }
+
+    /// Get a participant's voting weight (1 unless the admin set another).
+    async fn weight(&self, user_id: String) -> u64 {
+        self.state.participant_weights.get(&user_id).await.expect("get failed").unwrap_or(1)
+    }
+
+    /// Get the most participants the poll admits, the admin included (`None` = unlimited).
+    async fn max_participants(&self) -> Option<u32> {
+        *self.state.max_participants.get()
+    }
+
+    /// Get when the poll closes automatically, in microseconds.
+    async fn deadline(&self) -> Option<u64> {
+        *self.state.deadline_micros.get()
+    }
+
+    /// Get the microseconds left until the poll closes automatically (0 once the deadline passed).
+    async fn time_remaining_micros(&self) -> Option<u64> {
+        let now = self.runtime.system_time().micros();
+        self.state.deadline_micros.get().map(|deadline| deadline.saturating_sub(now))
+    }
+
+    /// Check if the decision deadline has passed.
+    async fn decision_overdue(&self) -> bool {
+        self.state.decision_deadline.get().is_some_and(|deadline| self.runtime.system_time() > deadline)
+    }
+
+    /// Get a shareable invite for this poll. The invite's poll code is the chain ID, as
+    /// entered on the client's join page.
+    async fn share_link(&self) -> state::ShareLink {
+        let topic = self.state.topic.get().clone();
+        let chain_id = self.runtime.chain_id();
+        let invite = format!("Join \"{}\" with poll code {}", topic, chain_id);
+        state::ShareLink {
+            chain_id,
+            topic,
+            status: self.state.status(self.runtime.system_time()),
+            invite,
+        }
+    }
+
     /// Get the computed results (available after close).
     async fn results(&self) -> Vec<state::ResultEntry> {
+        if self.ballots_hidden() {
+            return Vec::new();
+        }
         self.state.results.get().clone()
     }
 
-    /// Get all nominations.
-    async fn nominations(&self) -> Vec<state::NominationEntry> {
-        let mut nominations = Vec::new();
-        let indices = self.state.nominations.indices().await.expect("indices failed");
-        for id in indices {
-            if let Some(nomination) = self.state.nominations.get(&id).await.expect("get failed") {
-                nominations.push(state::NominationEntry {
-                    nomination_id: id,
-                    user_id: nomination.user_id,
-                    text: nomination.text,
+    /// Get how many participants voted, and how many of those abstained.
+    async fn turnout(&self) -> state::Turnout {
+        let eligible = self.state.participants.count().await.expect("count failed") as u32;
+        let voted = self.state.rankings.count().await.expect("count failed") as u32;
+        let percentage = if eligible == 0 { 0.0 } else { voted as f64 * 100.0 / eligible as f64 };
+        state::Turnout { eligible, voted, abstained: *self.state.abstentions.get(), percentage }
+    }
+
+    /// Get the number of voters who cast an empty ballot to abstain. Abstentions count as turnout
+    /// (and toward quorum) but give no points.
+    async fn abstention_count(&self) -> u32 {
+        *self.state.abstentions.get()
+    }
+
+    /// Get the top result once the poll is closed (`None` before). With a tied top score this is
+    /// the entry the tie-break favoured; see `tiedWinners` for all of them.
+    async fn winner(&self) -> Option<state::ResultEntry> {
+        if !*self.state.is_closed.get() {
+            return None;
+        }
+        self.state.results.get().first().cloned()
+    }
+
+    /// Explain why the winner won, including how often ballots were changed; `None` before the
+    /// poll is closed.
+    async fn winner_explanation(&self) -> Option<String> {
+        self.state.winner_explanation().await
+    }
+
+    /// Get the text of the nomination beating every other one head-to-head, computed at close.
+    /// `None` while the poll is open or when the ballots form a cycle.
+    async fn condorcet_winner(&self) -> Option<String> {
+        let nomination_id = self.state.condorcet_winner.get().clone()?;
+        self.nomination_entry(nomination_id).await.map(|entry| entry.text)
+    }
+
+    /// Get the top `n` results once the poll is closed (empty before).
+    async fn winners(&self, n: u32) -> Vec<state::ResultEntry> {
+        if !*self.state.is_closed.get() {
+            return Vec::new();
+        }
+        self.state.results.get().iter().take(n as usize).cloned().collect()
+    }
+
+    /// Get every result sharing the top score once the poll is closed (empty before).
+    async fn tied_winners(&self) -> Vec<state::ResultEntry> {
+        if !*self.state.is_closed.get() {
+            return Vec::new();
+        }
+        let results = self.state.results.get();
+        let Some(top) = results.first() else {
+            return Vec::new();
+        };
+        results
+            .iter()
+            .take_while(|entry| entry.score == top.score && entry.eliminated_in_round == top.eliminated_in_round)
+            .cloned()
+            .collect()
+    }
+
+    /// Get the results with a deterministic hash binding them to this chain, the admin and the
+    /// closing time, so the outcome can be verified off-chain.
+    async fn results_attestation(&self) -> state::ResultsAttestation {
+        let chain_id = self.runtime.chain_id();
+        let admin_id = self.state.admin_id.get().clone();
+        let closed_at = *self.state.closed_at.get();
+        let results = self.state.results.get().clone();
+        let preimage = serde_json::to_vec(&(&chain_id, &admin_id, &closed_at, &results))
+            .expect("results are serializable");
+        let hash = Sha256::digest(&preimage).iter().map(|byte| format!("{:02x}", byte)).collect();
+        state::ResultsAttestation {
+            chain_id,
+            admin_id,
+            closed_at,
+            results,
+            hash,
+        }
+    }
+
+    /// Check if "None of the above" won, meaning no meal was acceptable.
+    async fn no_acceptable_option(&self) -> bool {
+        *self.state.no_acceptable_option.get()
+    }
+
+    /// Get the independent results for one budgeted category.
+    async fn category_results(&self, category: String) -> Vec<state::ResultEntry> {
+        self.state.category_results.get(&category).await.expect("get failed").unwrap_or_default()
+    }
+
+    /// Get the runner-up result, e.g. as a backup if the winning option is unavailable.
+    async fn fallback_winner(&self) -> Option<state::ResultEntry> {
+        self.state.results.get().get(1).cloned()
+    }
+
+    /// Get the result in the given 1-based place, or `None` if there are fewer results.
+    /// Tied entries keep their order from the stored results.
+    async fn fallback_chain(&self, n: u32) -> Option<state::ResultEntry> {
+        let index = (n as usize).checked_sub(1)?;
+        self.state.results.get().get(index).cloned()
+    }
+
+    /// Split the results into at most `tiers` groups, cutting at the largest score gaps. Equal
+    /// scores are never split apart.
+    async fn result_tiers(&self, #[graphql(default = 3)] tiers: u32) -> Vec<Vec<state::ResultEntry>> {
+        let results = self.state.results.get();
+        if results.is_empty() {
+            return Vec::new();
+        }
+        let mut gaps: Vec<(u64, usize)> = results
+            .windows(2)
+            .enumerate()
+            .map(|(i, pair)| (pair[0].score.saturating_sub(pair[1].score), i + 1))
+            .filter(|(gap, _)| *gap > 0)
+            .collect();
+        gaps.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        let mut cuts: Vec<usize> = gaps.into_iter().take((tiers as usize).saturating_sub(1)).map(|(_, i)| i).collect();
+        cuts.sort_unstable();
+        let mut grouped = Vec::new();
+        let mut start = 0;
+        for cut in cuts.into_iter().chain([results.len()]) {
+            grouped.push(results[start..cut].to_vec());
+            start = cut;
+        }
+        grouped
+    }
+
+    /// Get how results are computed when the poll closes.
+    async fn tally_method(&self) -> TallyMethod {
+        *self.state.tally_method.get()
+    }
+
+    /// Get a voter's rejected ballots with the reason each was refused, oldest first (recorded only
+    /// under `record_rejected_ballots`).
+    async fn my_rejected_ballots(&self, user_id: String) -> Vec<state::RejectedBallot> {
+        self.state.rejected_ballots.get(&user_id).await.expect("get failed").unwrap_or_default()
+    }
+
+    /// Get audit log entries, oldest first, optionally only those of one operation type (e.g.
+    /// `"Vote"`). `offset` and `limit` page through the matching entries.
+    async fn audit_log(
+        &self,
+        #[graphql(default)] offset: u32,
+        #[graphql(default = 50)] limit: u32,
+        operation_type: Option<String>,
+    ) -> Vec<state::AuditEntry> {
+        let (offset, limit) = (offset as usize, limit as usize);
+        let count = self.state.audit_log.count();
+        match operation_type {
+            None => {
+                let start = offset.min(count);
+                let end = start.saturating_add(limit).min(count);
+                self.state.audit_log.read(start..end).await.expect("read failed")
+            }
+            Some(operation_type) => {
+                let entries = self.state.audit_log.read(0..count).await.expect("read failed");
+                entries
+                    .into_iter()
+                    .filter(|entry| entry.operation_type == operation_type)
+                    .skip(offset)
+                    .take(limit)
+                    .collect()
+            }
+        }
+    }
+
+    /// Combine the results last reported by the given poll chains (see `collectResults`) into one
+    /// ranking, summing the scores of nominations whose text matches ignoring case and spacing.
+    /// Entries use the normalized text as their ID.
+    async fn aggregate_results(&self, chain_ids: Vec<ChainId>) -> Vec<state::ResultEntry> {
+        let mut combined: BTreeMap<String, state::ResultEntry> = BTreeMap::new();
+        for chain_id in chain_ids {
+            let results = self.state.poll_results.get(&chain_id).await.expect("get failed").unwrap_or_default();
+            for entry in results {
+                let key = entry.nomination_text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+                let aggregate = combined.entry(key.clone()).or_insert_with(|| state::ResultEntry {
+                    nomination_id: key,
+                    nomination_text: entry.nomination_text.trim().to_string(),
+                    score: 0,
+                    eliminated_in_round: None,
+                    first_choice_votes: 0,
+                    elected: false,
                 });
+                aggregate.score = aggregate.score.saturating_add(entry.score);
+                aggregate.first_choice_votes = aggregate.first_choice_votes.saturating_add(entry.first_choice_votes);
             }
         }
-        nominations
+        let mut ranking: Vec<state::ResultEntry> = combined.into_values().collect();
+        ranking.sort_by(|a, b| b.score.cmp(&a.score));
+        ranking
     }
 
-    /// Get all participants.
-    async fn participants(&self) -> Vec<state::ParticipantEntry> {
-        let mut participants = Vec::new();
-        let indices = self.state.participants.indices().await.expect("indices failed");
+    /// Get every published result set, oldest first: the original tally and any tie-break re-draws.
+    async fn results_history(&self) -> Vec<Vec<state::ResultEntry>> {
+        let count = self.state.results_history.count();
+        self.state.results_history.read(0..count).await.expect("read failed")
+    }
+
+    /// Gini coefficient of the result scores: near 0 when support is evenly split, near 1 when
+    /// one option dominates.
+    async fn inequality_index(&self) -> f64 {
+        let scores: Vec<u64> = self.state.results.get().iter().map(|entry| entry.score).collect();
+        gini_coefficient(&scores)
+    }
+
+    /// A canonical JSON snapshot of the poll for archiving and third-party verification: topic,
+    /// tally method, every ballot in voter order (by salted hash under `anonymous_ballots`) and
+    /// the ranked results. Object keys are sorted,
+    /// so the same poll always exports byte-identical output. Ballots and results are left empty
+    /// while `hide_ballots_until_close` keeps them secret.
+    async fn results_export(&self) -> String {
+        let mut ballots = BTreeMap::new();
+        let mut results = Vec::new();
+        if !self.ballots_hidden() {
+            let indices = self.state.rankings.indices().await.expect("indices failed");
+            for user_id in indices {
+                if let Some(ballot) = self.state.rankings.get(&user_id).await.expect("get failed") {
+                    ballots.insert(self.voter_label(&user_id), ballot);
+                }
+            }
+            results = self.state.results.get().clone();
+        }
+        let export = serde_json::json!({
+            "topic": self.state.topic.get(),
+            "tally_method": self.state.tally_method.get(),
+            "ballots": ballots,
+            "results": results,
+        });
+        serde_json::to_string(&export).expect("export serializes")
+    }
+
+    /// Kendall tau rank correlation between two users' ballots: 1.0 for identical orderings,
+    /// -1.0 for reversed ones. Options missing from a ballot count as tied below everything it
+    /// ranks; 0.0 when either user has not voted, no pair of options can be compared, or
+    /// `anonymous_ballots` keeps ballots from being tied to users.
+    async fn ballot_similarity(&self, a: String, b: String) -> f64 {
+        if self.ballots_hidden() || *self.state.anonymous_ballots.get() {
+            return 0.0;
+        }
+        let ballot_a = self.state.rankings.get(&a).await.expect("get failed").unwrap_or_default();
+        let ballot_b = self.state.rankings.get(&b).await.expect("get failed").unwrap_or_default();
+        kendall_tau(&ballot_a, &ballot_b)
+    }
+
+    /// The ordering of all nominations that disagrees with the fewest pairwise ballot preferences.
+    /// Exact search, so `None` above `KEMENY_MAX_NOMINATIONS` nominations.
+    async fn kemeny_ranking(&self) -> Option<Vec<String>> {
+        if self.ballots_hidden() {
+            return None;
+        }
+        let (nomination_ids, matrix) = self.pairwise_matrix().await;
+        if nomination_ids.len() > KEMENY_MAX_NOMINATIONS {
+            return None;
+        }
+        let disagreement = |order: &[usize]| {
+            let mut total = 0u64;
+            for (i, &ahead) in order.iter().enumerate() {
+                for &behind in &order[i + 1..] {
+                    total += matrix[behind][ahead] as u64;
+                }
+            }
+            total
+        };
+        let mut order: Vec<usize> = (0..nomination_ids.len()).collect();
+        let mut best = (disagreement(&order), order.clone());
+        while next_permutation(&mut order) {
+            let cost = disagreement(&order);
+            if cost < best.0 {
+                best = (cost, order.clone());
+            }
+        }
+        Some(best.1.into_iter().map(|i| nomination_ids[i].clone()).collect())
+    }
+
+    /// Count ranked ballots that place `nomination_id` within their first `k` choices.
+    async fn top_k_appearances(&self, nomination_id: String, k: u32) -> u32 {
+        let mut count = 0;
+        if self.ballots_hidden() {
+            return count;
+        }
+        let indices = self.state.rankings.indices().await.expect("indices failed");
         for user_id in indices {
-            if let Some(name) = self.state.participants.get(&user_id).await.expect("get failed") {
-                participants.push(state::ParticipantEntry {
-                    user_id,
-                    name,
-                });
+            if let Some(ballot) = self.state.rankings.get(&user_id).await.expect("get failed") {
+                if ballot.iter().take(k as usize).any(|id| *id == nomination_id) {
+                    count += 1;
+                }
             }
         }
-        participants
+        count
     }
 
-    /// Get the participant count.
-    async fn participant_count(&self) -> u32 {
-        self.state.participants.count().await.unwrap_or(0) as u32
+    /// Min, max, mean and median ballot length across ranked ballots; `None` before any vote.
+    async fn ranking_depth_stats(&self) -> Option<state::RankingDepthStats> {
+        if self.ballots_hidden() {
+            return None;
+        }
+        let mut depths = Vec::new();
+        let indices = self.state.rankings.indices().await.expect("indices failed");
+        for user_id in indices {
+            if let Some(ballot) = self.state.rankings.get(&user_id).await.expect("get failed") {
+                depths.push(ballot.len() as u32);
+            }
+        }
+        depths.sort_unstable();
+        let (&min, &max) = (depths.first()?, depths.last()?);
+        let mean = depths.iter().map(|depth| *depth as f64).sum::<f64>() / depths.len() as f64;
+        let middle = depths.len() / 2;
+        let median = if depths.len() % 2 == 0 {
+            (depths[middle - 1] + depths[middle]) as f64 / 2.0
+        } else {
+            depths[middle] as f64
+        };
+        Some(state::RankingDepthStats { min, max, mean, median })
     }
 
-    /// Get valid chain IDs created by a user.
-    async fn created_polls(&self, user_id: String) -> Vec<ChainId> {
-        self.state
-            .created_polls
-            .get(&user_id)
-            .await
-            .expect("get failed")
-            .unwrap_or_default()
+    /// User IDs whose ranked ballots put the winner within their first `k` choices (first
+    /// choice by default). Empty before results exist.
+    async fn winner_supporters(&self, #[graphql(default = 1)] k: u32) -> Vec<String> {
+        if self.ballots_hidden() {
+            return Vec::new();
+        }
+        let Some(winner) = self.state.results.get().first() else {
+            return Vec::new();
+        };
+        let mut supporters = Vec::new();
+        let indices = self.state.rankings.indices().await.expect("indices failed");
+        for user_id in indices {
+            if let Some(ballot) = self.state.rankings.get(&user_id).await.expect("get failed") {
+                if ballot.iter().take(k as usize).any(|id| *id == winner.nomination_id) {
+                    supporters.push(self.voter_label(&user_id));
+                }
+            }
+        }
+        if *self.state.anonymous_ballots.get() {
+            supporters.sort();
+        }
+        supporters
     }
 
-    /// Get all rankings (votes).
-    async fn rankings(&self) -> Vec<state::RankingEntry> {
-        let mut rankings = Vec::new();
+    /// Count ballots that rank only a single option even though more could have been ranked.
+    async fn bullet_vote_count(&self) -> u32 {
+        if self.ballots_hidden() || *self.state.votes_per_voter.get() <= 1 || self.state.nominations.count().await.unwrap_or(0) <= 1 {
+            return 0;
+        }
+        let mut count = 0;
         let indices = self.state.rankings.indices().await.expect("indices failed");
         for user_id in indices {
             if let Some(nomination_ids) = self.state.rankings.get(&user_id).await.expect("get failed") {
-                rankings.push(state::RankingEntry {
-                    user_id,
-                    nomination_ids,
-                });
+                if nomination_ids.len() == 1 {
+                    count += 1;
+                }
             }
         }
-        rankings
+        count
     }
-}
 
-struct MutationRoot {
-    runtime: Arc<ServiceRuntime<MealVotingService>>,
-}
+    /// Smallest number of voters who, by switching to the runner-up, would change the winner.
+    /// `None` if there is no runner-up or no set of ballots could flip the result.
+    async fn flip_margin(&self) -> Option<u32> {
+        let coalition = self.flip_coalition().await?;
+        Some(coalition.len() as u32)
+    }
 
-#[Object]
-impl MutationRoot {
-    /// Create a new poll.
-    async fn create_poll(&self, topic: String, votes_per_voter: u32, owner: String) -> bool {
-        println!("SERVICE: create_poll");
-        let operation = Operation::CreatePoll { topic, votes_per_voter, owner };
-        self.runtime.schedule_operation(&operation);
-        true
+    /// The voters in a smallest group who, by switching to the runner-up, would change the
+    /// winner, largest swing first. `None` under the same conditions as `flip_margin`.
+    async fn minimal_flip_coalition(&self) -> Option<Vec<String>> {
+        let coalition = self.flip_coalition().await?;
+        Some(coalition.iter().map(|user_id| self.voter_label(user_id)).collect())
     }
 
+    /// Get the nomination IDs voters may rank (empty when every nomination is eligible).
+    async fn shortlist(&self) -> Vec<String> {
+        self.state.shortlist.get().clone()
+    }
 
-    /// Join the poll as a participant.
-    async fn join(&self, name: String, owner: String) -> bool {
-        println!("SERVICE: join name={} owner={}", name, owner);
-        let operation = Operation::Join { name, owner };
-        self.runtime.schedule_operation(&operation);
-        println!("SERVICE: join scheduled");
-        true
+    /// Leave-one-out influence of each voter on the winner's margin, most influential first.
+    async fn voter_influence(&self) -> Vec<state::VoterInfluence> {
+        if self.ballots_hidden() {
+            return Vec::new();
+        }
+        let full = self.state.tally_scores(None).await;
+        let Some(winner) = full.iter().max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0))).map(|(id, _)| id.clone()) else {
+            return Vec::new();
+        };
+        let margin = |scores: &BTreeMap<String, u64>| {
+            let own = scores.get(&winner).copied().unwrap_or(0) as i64;
+            let best_other = scores.iter().filter(|(id, _)| **id != winner).map(|(_, score)| *score as i64).max();
+            own - best_other.unwrap_or(0)
+        };
+        let full_margin = margin(&full);
+        let mut voters: Vec<String> =
+            self.state.voter_ballots().await.into_iter().map(|(user_id, _)| user_id).collect();
+        voters.extend(self.state.point_allocations.indices().await.expect("indices failed"));
+        voters.extend(self.state.star_ratings.indices().await.expect("indices failed"));
+        voters.sort();
+        voters.dedup();
+        let mut influences = Vec::new();
+        for user_id in voters {
+            let without = margin(&self.state.tally_scores(Some(&user_id)).await);
+            let user_id = self.voter_label(&user_id);
+            influences.push(state::VoterInfluence { influence: full_margin - without, pivotal: without <= 0, user_id });
+        }
+        influences.sort_by(|a, b| b.influence.cmp(&a.influence).then_with(|| a.user_id.cmp(&b.user_id)));
+        influences
     }
 
-    /// Add a nomination.
-    async fn nominate(&self, text: String, owner: String) -> bool {
-        let operation = Operation::Nominate { text, owner };
-        self.runtime.schedule_operation(&operation);
-        true
+    /// How far down each ranked ballot the winner sits, and the average across ballots. A high
+    /// average suggests the winner is a poor fit for the group. Empty before results exist.
+    async fn voter_regret(&self) -> state::RegretReport {
+        let mut voters = Vec::new();
+        let winner = if self.ballots_hidden() { None } else { self.state.results.get().first() };
+        if let Some(winner) = winner {
+            let indices = self.state.rankings.indices().await.expect("indices failed");
+            for user_id in indices {
+                if let Some(ballot) = self.state.rankings.get(&user_id).await.expect("get failed") {
+                    let regret = ballot.iter().position(|id| *id == winner.nomination_id).unwrap_or(ballot.len());
+                    voters.push(state::VoterRegret { user_id: self.voter_label(&user_id), regret: regret as u32 });
+                }
+            }
+        }
+        if *self.state.anonymous_ballots.get() {
+            voters.sort_by(|a, b| a.user_id.cmp(&b.user_id));
+        }
+        let average = if voters.is_empty() {
+            0.0
+        } else {
+            voters.iter().map(|voter| voter.regret as f64).sum::<f64>() / voters.len() as f64
+        };
+        state::RegretReport { voters, average }
     }
 
-    /// Submit vote rankings.
-    async fn vote(&self, rankings: Vec<String>, owner: String) -> bool {
-        let operation = Operation::Vote { rankings, owner };
-        self.runtime.schedule_operation(&operation);
-        true
+    /// Position-by-nomination matrix of ranked ballots: for each rank position, how many ballots
+    /// put each nomination there. Contains no voter identities.
+    async fn aggregate_ballots(&self) -> Vec<state::PositionCounts> {
+        let mut positions: Vec<BTreeMap<String, u32>> = Vec::new();
+        if self.ballots_hidden() {
+            return Vec::new();
+        }
+        let indices = self.state.rankings.indices().await.expect("indices failed");
+        for user_id in indices {
+            if let Some(ballot) = self.state.rankings.get(&user_id).await.expect("get failed") {
+                for (position, nomination_id) in ballot.into_iter().enumerate() {
+                    if positions.len() <= position {
+                        positions.resize_with(position + 1, BTreeMap::new);
+                    }
+                    *positions[position].entry(nomination_id).or_insert(0) += 1;
+                }
+            }
+        }
+        positions
+            .into_iter()
+            .enumerate()
+            .map(|(position, counts)| state::PositionCounts {
+                position: position as u32,
+                counts: counts
+                    .into_iter()
+                    .map(|(nomination_id, count)| state::NominationCount { nomination_id, count })
+                    .collect(),
+            })
+            .collect()
     }
 
-    /// Start the voting phase (admin only).
-    async fn start_vote(&self, owner: String) -> bool {
-        let operation = Operation::StartVote { owner };
-        self.runtime.schedule_operation(&operation);
-        true
+    /// Check internal consistency and describe every violation found: ballots naming unknown
+    /// nominations or cast by non-participants, nomination IDs at or past the ID counter, and
+    /// stored Borda results that disagree with a fresh tally. Other tally methods and results
+    /// merged from shards aren't recounted. Empty when the state is consistent.
+    async fn check_invariants(&self) -> Vec<String> {
+        let mut violations = Vec::new();
+        let nomination_ids = self.state.nominations.indices().await.expect("indices failed");
+        let next_id = *self.state.next_nomination_id.get();
+        for nomination_id in &nomination_ids {
+            let counter = nomination_id.strip_prefix("nom_").and_then(|n| n.parse::<u64>().ok());
+            if counter.is_some_and(|n| n >= next_id) {
+                violations.push(format!("Nomination {} is not below the ID counter {}", nomination_id, next_id));
+            }
+        }
+        let indices = self.state.rankings.indices().await.expect("indices failed");
+        for user_id in indices {
+            let ballot = self.state.rankings.get(&user_id).await.expect("get failed").unwrap_or_default();
+            for nomination_id in ballot.iter().filter(|id| !nomination_ids.contains(id)) {
+                violations.push(format!("Ballot of {} ranks unknown nomination {}", user_id, nomination_id));
+            }
+            if !self.state.participants.contains_key(&user_id).await.expect("contains failed") {
+                violations.push(format!("Ballot cast by non-participant {}", user_id));
+            }
+        }
+        let indices = self.state.point_allocations.indices().await.expect("indices failed");
+        for user_id in indices {
+            if !self.state.participants.contains_key(&user_id).await.expect("contains failed") {
+                violations.push(format!("Points allocated by non-participant {}", user_id));
+            }
+        }
+        let indices = self.state.star_ratings.indices().await.expect("indices failed");
+        for user_id in indices {
+            if !self.state.participants.contains_key(&user_id).await.expect("contains failed") {
+                violations.push(format!("Ratings given by non-participant {}", user_id));
+            }
+        }
+        let results = self.state.results.get();
+        let merged = self.state.partial_results.count().await.expect("count failed") > 0;
+        let recountable = *self.state.tally_method.get() == TallyMethod::Borda && !merged;
+        if recountable && !results.is_empty() {
+            let mut expected = self.state.tally_scores(None).await;
+            let shortlist = self.state.shortlist.get();
+            if !shortlist.is_empty() {
+                expected.retain(|nomination_id, _| shortlist.contains(nomination_id));
+            }
+            let stored: BTreeMap<String, u64> =
+                results.iter().map(|entry| (entry.nomination_id.clone(), entry.score)).collect();
+            if stored != expected {
+                violations.push("Stored results do not match a fresh tally".to_string());
+            }
+        }
+        violations
     }
 
-    /// Close the poll and compute results (admin only).
-    async fn close_poll(&self, owner: String) -> bool {
-        let operation = Operation::ClosePoll { owner };
-        self.runtime.schedule_operation(&operation);
-        true
+    /// Get each ranked nomination with its nominator, score and place, in result order. Tied
+    /// scores share a place and the next one skips ahead (1, 1, 3).
+    async fn nomination_results(&self) -> Vec<state::NominationResult> {
+        if self.ballots_hidden() {
+            return Vec::new();
+        }
+        let results = self.state.results.get();
+        let mut entries: Vec<state::NominationResult> = Vec::with_capacity(results.len());
+        for (i, entry) in results.iter().enumerate() {
+            let rank = match entries.last() {
+                Some(previous) if previous.score == entry.score => previous.rank,
+                _ => i as u32 + 1,
+            };
+            let user_id = self
+                .state
+                .nominations
+                .get(&entry.nomination_id)
+                .await
+                .expect("get failed")
+                .map(|nomination| nomination.user_id)
+                .unwrap_or_default();
+            entries.push(state::NominationResult {
+                nomination_id: entry.nomination_id.clone(),
+                text: entry.nomination_text.clone(),
+                user_id,
+                score: entry.score,
+                rank,
+            });
+        }
+        entries
+    }
+
+    /// Get nominations in storage key order, `limit` at a time starting at `offset`.
+    async fn nominations(
+        &self,
+        #[graphql(default)] offset: u32,
+        #[graphql(default = 50)] limit: u32,
+    ) -> Vec<state::NominationEntry> {
+        let mut nominations = Vec::new();
+        let indices = self.state.nominations.indices().await.expect("indices failed");
+        for id in indices.into_iter().skip(offset as usize).take(limit as usize) {
+            nominations.extend(self.nomination_entry(id).await);
+        }
+        nominations
+    }
+
+    /// Get a single nomination by ID.
+    async fn nomination(&self, nomination_id: String) -> Option<state::NominationEntry> {
+        self.nomination_entry(nomination_id).await
+    }
+
+    /// Get the nominations carrying a dietary tag (case-insensitive).
+    async fn nominations_with_tag(&self, tag: String) -> Vec<state::NominationEntry> {
+        let tag = tag.trim().to_lowercase();
+        let mut nominations = self.nomination_entries().await;
+        nominations.retain(|nomination| nomination.tags.contains(&tag));
+        nominations
+    }
+
+    /// Get the comments on a nomination, oldest first.
+    async fn comments(&self, nomination_id: String) -> Vec<state::Comment> {
+        self.state
+            .comments
+            .get(&nomination_id)
+            .await
+            .expect("get failed")
+            .unwrap_or_default()
+    }
+
+    /// Get participants in storage key order, `limit` at a time starting at `offset`.
+    async fn participants(
+        &self,
+        #[graphql(default)] offset: u32,
+        #[graphql(default = 50)] limit: u32,
+    ) -> Vec<state::ParticipantEntry> {
+        let mut participants = Vec::new();
+        let indices = self.state.participants.indices().await.expect("indices failed");
+        for user_id in indices.into_iter().skip(offset as usize).take(limit as usize) {
+            if let Some(name) = self.state.participants.get(&user_id).await.expect("get failed") {
+                participants.push(state::ParticipantEntry {
+                    user_id,
+                    name,
+                });
+            }
+        }
+        participants
+    }
+
+    /// Get participants who joined but never nominated or cast a ballot.
+    async fn inactive_participants(&self) -> Vec<state::ParticipantEntry> {
+        let mut active = BTreeSet::new();
+        let indices = self.state.nominations.indices().await.expect("indices failed");
+        for id in indices {
+            if let Some(nomination) = self.state.nominations.get(&id).await.expect("get failed") {
+                active.insert(nomination.user_id);
+            }
+        }
+        active.extend(self.state.rankings.indices().await.expect("indices failed"));
+        active.extend(self.state.point_allocations.indices().await.expect("indices failed"));
+        active.extend(self.state.star_ratings.indices().await.expect("indices failed"));
+
+        let mut inactive = Vec::new();
+        let indices = self.state.participants.indices().await.expect("indices failed");
+        for user_id in indices.into_iter().filter(|user_id| !active.contains(user_id)) {
+            if let Some(name) = self.state.participants.get(&user_id).await.expect("get failed") {
+                inactive.push(state::ParticipantEntry { user_id, name });
+            }
+        }
+        inactive
+    }
+
+    /// Get the number of operations executed on this chain.
+    async fn operation_count(&self) -> u64 {
+        *self.state.operation_count.get()
+    }
+
+    /// Get the number of participant messages dropped for exceeding the rate limit.
+    async fn dropped_messages(&self) -> u64 {
+        *self.state.dropped_messages.get()
+    }
+
+    /// Rough fee estimate: operations executed so far times the configured per-operation cost.
+    async fn estimated_fees(&self) -> Amount {
+        let operation_count = *self.state.operation_count.get() as u128;
+        self.state.fee_per_operation.get().saturating_mul(operation_count)
+    }
+
+    /// Get the participant whose ballot counts for `user_id` if they cast none.
+    async fn delegation(&self, user_id: String) -> Option<String> {
+        self.state.delegations.get(&user_id).await.expect("get failed")
+    }
+
+    /// Get the participant count.
+    async fn participant_count(&self) -> u32 {
+        self.state.participants.count().await.unwrap_or(0) as u32
+    }
+
+    /// Get the last results reported by a poll chain created or collected from here.
+    async fn poll_results(&self, chain_id: ChainId) -> Vec<state::ResultEntry> {
+        self.state.poll_results.get(&chain_id).await.expect("get failed").unwrap_or_default()
+    }
+
+    /// Get poll chains a user has joined, as reported back to this factory chain.
+    async fn joined_polls(&self, user_id: String) -> Vec<ChainId> {
+        self.state
+            .joined_polls
+            .get(&user_id)
+            .await
+            .expect("get failed")
+            .unwrap_or_default()
+    }
+
+    /// Get valid chain IDs created by a user.
+    async fn created_polls(&self, user_id: String) -> Vec<ChainId> {
+        self.state
+            .created_polls
+            .get(&user_id)
+            .await
+            .expect("get failed")
+            .unwrap_or_default()
+    }
+
+    /// Get rankings (votes) in storage key order, `limit` at a time starting at `offset`. Under
+    /// `anonymous_ballots` each `user_id` is a salted hash, entries are ordered by it and
+    /// `voted_at` is omitted, since key order or timing would give the voter away.
+    async fn rankings(
+        &self,
+        #[graphql(default)] offset: u32,
+        #[graphql(default = 50)] limit: u32,
+    ) -> Vec<state::RankingEntry> {
+        let mut rankings = Vec::new();
+        if self.ballots_hidden() {
+            return rankings;
+        }
+        let anonymous = *self.state.anonymous_ballots.get();
+        let indices = self.state.rankings.indices().await.expect("indices failed");
+        let mut voters: Vec<(String, String)> =
+            indices.into_iter().map(|user_id| (self.voter_label(&user_id), user_id)).collect();
+        if anonymous {
+            voters.sort();
+        }
+        for (label, user_id) in voters.into_iter().skip(offset as usize).take(limit as usize) {
+            if let Some(nomination_ids) = self.state.rankings.get(&user_id).await.expect("get failed") {
+                let voted_at = if anonymous {
+                    None
+                } else {
+                    self.state.ranking_times.get(&user_id).await.expect("get failed")
+                };
+                let revisions = self.state.vote_revisions.get(&user_id).await.expect("get failed").unwrap_or(0);
+                rankings.push(state::RankingEntry {
+                    user_id: label,
+                    nomination_ids,
+                    voted_at,
+                    revisions,
+                });
+            }
+        }
+        rankings
+    }
+}
+
+/// Points assigned to one nomination in a cumulative-voting ballot.
+#[derive(InputObject)]
+struct PointAllocation {
+    nomination_id: String,
+    points: u64,
+}
+
+/// Stars given to one nomination in a score-voting ballot.
+#[derive(InputObject)]
+struct StarRating {
+    nomination_id: String,
+    stars: u8,
+}
+
+impl QueryRoot {
+    /// Every nomination with its endorsement count, in nomination ID order.
+    async fn nomination_entries(&self) -> Vec<state::NominationEntry> {
+        let mut nominations = Vec::new();
+        let indices = self.state.nominations.indices().await.expect("indices failed");
+        for id in indices {
+            nominations.extend(self.nomination_entry(id).await);
+        }
+        nominations
+    }
+
+    /// Whether `hide_ballots_until_close` is keeping ballots secret right now. Every query reading
+    /// ballot contents or a tally checks it.
+    fn ballots_hidden(&self) -> bool {
+        *self.state.hide_ballots_until_close.get() && !*self.state.is_closed.get()
+    }
+
+    /// How a voter is shown next to ballot data: their user ID, or under `anonymous_ballots` a
+    /// hash of it salted with the poll's `ballot_salt`. The same voter gets the same hash in every
+    /// query of one poll. The salt comes from public chain data, so anyone holding the participant
+    /// list can recompute the labels: this hides IDs from casual readers, not from a determined one.
+    fn voter_label(&self, user_id: &str) -> String {
+        if !*self.state.anonymous_ballots.get() {
+            return user_id.to_string();
+        }
+        let preimage =
+            serde_json::to_vec(&(self.state.ballot_salt.get(), user_id)).expect("user IDs are serializable");
+        Sha256::digest(&preimage).iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// One nomination with its endorsement count, if it exists.
+    async fn nomination_entry(&self, id: String) -> Option<state::NominationEntry> {
+        let nomination = self.state.nominations.get(&id).await.expect("get failed")?;
+        let endorsers = self.state.endorsements.get(&id).await.expect("get failed").unwrap_or_default();
+        Some(state::NominationEntry {
+            nomination_id: id,
+            user_id: nomination.user_id,
+            text: nomination.text,
+            endorsement_count: endorsers.len() as u32,
+            base_score: nomination.base_score,
+            category: nomination.category,
+            tags: nomination.tags,
+        })
+    }
+
+    /// Nomination IDs and the pairwise preference matrix over them: `matrix[i][j]` counts ranked
+    /// ballots placing nomination `i` above nomination `j`. A ranked option beats an unranked one;
+    /// two unranked options are not compared.
+    async fn pairwise_matrix(&self) -> (Vec<String>, Vec<Vec<u32>>) {
+        let nomination_ids = self.state.nominations.indices().await.expect("indices failed");
+        let n = nomination_ids.len();
+        let mut matrix = vec![vec![0u32; n]; n];
+        let indices = self.state.rankings.indices().await.expect("indices failed");
+        for user_id in indices {
+            let Some(ballot) = self.state.rankings.get(&user_id).await.expect("get failed") else {
+                continue;
+            };
+            let positions: Vec<Option<usize>> =
+                nomination_ids.iter().map(|id| ballot.iter().position(|ranked| ranked == id)).collect();
+            for i in 0..n {
+                for j in 0..n {
+                    let prefers = match (positions[i], positions[j]) {
+                        (Some(a), Some(b)) => a < b,
+                        (Some(_), None) => true,
+                        _ => false,
+                    };
+                    if prefers {
+                        matrix[i][j] += 1;
+                    }
+                }
+            }
+        }
+        (nomination_ids, matrix)
+    }
+
+    /// Greedily take the largest ballot swings until they overcome the winner's lead, which
+    /// yields a smallest flipping coalition.
+    async fn flip_coalition(&self) -> Option<Vec<String>> {
+        if self.ballots_hidden() {
+            return None;
+        }
+        let results = self.state.results.get();
+        let (winner, runner_up) = match results.as_slice() {
+            [winner, runner_up, ..] => (winner, runner_up),
+            _ => return None,
+        };
+        let gap = winner.score.saturating_sub(runner_up.score);
+        let swings = self.ballot_swings(&winner.nomination_id, &runner_up.nomination_id).await;
+        let mut coalition = Vec::new();
+        let mut closed = 0u64;
+        for (user_id, swing) in swings {
+            closed = closed.saturating_add(swing);
+            coalition.push(user_id);
+            if closed > gap {
+                return Some(coalition);
+            }
+        }
+        None
+    }
+
+    /// For each voter, how much the winner's lead over the runner-up shrinks if they move their
+    /// whole ballot to the runner-up: the points they gave the winner plus the points they
+    /// withheld from the runner-up, on the poll's Borda curve and times the voter's weight.
+    /// Sorted largest swing first.
+    async fn ballot_swings(&self, winner: &str, runner_up: &str) -> Vec<(String, u64)> {
+        let max_points = *self.state.votes_per_voter.get() as u64;
+        let variant = *self.state.borda_variant.get();
+        let top_points = variant.points(max_points, 0);
+        let borda_points = |ballot: &[String], nomination_id: &str| {
+            ballot
+                .iter()
+                .position(|id| id == nomination_id)
+                .map_or(0, |i| variant.points(max_points, i))
+        };
+        let mut swings = Vec::new();
+        for (user_id, ballot) in self.state.voter_ballots().await {
+            let weight = self.state.participant_weights.get(&user_id).await.expect("get failed").unwrap_or(1);
+            let withheld = top_points.saturating_sub(borda_points(&ballot, runner_up));
+            let swing = borda_points(&ballot, winner).saturating_add(withheld).saturating_mul(weight);
+            swings.push((user_id, swing));
+        }
+        let indices = self.state.point_allocations.indices().await.expect("indices failed");
+        for user_id in indices {
+            if let Some(allocations) = self.state.point_allocations.get(&user_id).await.expect("get failed") {
+                let points_for = |nomination_id: &str| {
+                    allocations
+                        .iter()
+                        .filter(|(id, _)| id == nomination_id)
+                        .map(|(_, points)| *points)
+                        .sum::<u64>()
+                };
+                let swing = points_for(winner) + max_points.saturating_sub(points_for(runner_up));
+                swings.push((user_id, swing));
+            }
+        }
+        swings.sort_by(|a, b| b.1.cmp(&a.1));
+        swings
+    }
+}
+
+/// Gini coefficient of a set of non-negative scores (0.0 for an empty or all-zero set).
+fn gini_coefficient(scores: &[u64]) -> f64 {
+    let total: f64 = scores.iter().map(|score| *score as f64).sum();
+    if scores.is_empty() || total == 0.0 {
+        return 0.0;
+    }
+    let mut sorted = scores.to_vec();
+    sorted.sort_unstable();
+    let n = sorted.len() as f64;
+    let weighted: f64 = sorted
+        .iter()
+        .enumerate()
+        .map(|(i, score)| (i as f64 + 1.0) * *score as f64)
+        .sum();
+    (2.0 * weighted) / (n * total) - (n + 1.0) / n
+}
+
+/// Advance `order` to the next lexicographic permutation; `false` once it was the last one.
+fn next_permutation(order: &mut [usize]) -> bool {
+    let Some(pivot) = order.windows(2).rposition(|pair| pair[0] < pair[1]) else {
+        return false;
+    };
+    let successor = order.iter().rposition(|&value| value > order[pivot]).expect("pivot has a successor");
+    order.swap(pivot, successor);
+    order[pivot + 1..].reverse();
+    true
+}
+
+/// Kendall tau between two (possibly partial) rankings over the union of their options.
+fn kendall_tau(a: &[String], b: &[String]) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let mut options: Vec<&String> = a.iter().chain(b).collect();
+    options.sort();
+    options.dedup();
+    let rank = |ballot: &[String], option: &String| {
+        ballot.iter().position(|id| id == option).unwrap_or(ballot.len())
+    };
+    let (mut concordant, mut discordant) = (0i64, 0i64);
+    for (i, first) in options.iter().enumerate() {
+        for second in &options[i + 1..] {
+            let order_a = rank(a, first).cmp(&rank(a, second));
+            let order_b = rank(b, first).cmp(&rank(b, second));
+            if order_a.is_eq() || order_b.is_eq() {
+                continue;
+            }
+            if order_a == order_b {
+                concordant += 1;
+            } else {
+                discordant += 1;
+            }
+        }
+    }
+    if concordant + discordant == 0 {
+        return 0.0;
+    }
+    (concordant - discordant) as f64 / (concordant + discordant) as f64
+}
+
+struct MutationRoot {
+    runtime: Arc<ServiceRuntime<MealVotingService>>,
+}
+
+#[Object]
+impl MutationRoot {
+    /// Create a new poll.
+    async fn create_poll(
+        &self,
+        topic: String,
+        votes_per_voter: u32,
+        owner: String,
+        config: Option<PollConfig>,
+    ) -> async_graphql::Result<bool> {
+        println!("SERVICE: create_poll");
+        if topic.trim().is_empty() {
+            return Err(Error::new("topic must not be empty"));
+        }
+        if votes_per_voter == 0 {
+            return Err(Error::new("votes per voter must be at least 1"));
+        }
+        let config = config.unwrap_or_default();
+        let operation = Operation::CreatePoll { topic, votes_per_voter, owner, config };
+        self.schedule(operation)
+    }
+
+
+    /// Join the poll as a participant.
+    async fn join(&self, name: String, owner: String) -> async_graphql::Result<bool> {
+        println!("SERVICE: join name={} owner={}", name, owner);
+        if name.trim().is_empty() {
+            return Err(Error::new("name must not be empty"));
+        }
+        let operation = Operation::Join { name, owner };
+        self.schedule(operation)?;
+        println!("SERVICE: join scheduled");
+        Ok(true)
+    }
+
+    /// Add a nomination.
+    async fn nominate(
+        &self,
+        text: String,
+        category: Option<String>,
+        #[graphql(default)] tags: Vec<String>,
+        owner: String,
+    ) -> async_graphql::Result<bool> {
+        if text.trim().is_empty() {
+            return Err(Error::new("nomination text must not be empty"));
+        }
+        let operation = Operation::Nominate { text, category, tags, owner };
+        self.schedule(operation)
+    }
+
+    /// Add several nominations at once; none are added if any is refused.
+    async fn nominate_batch(&self, texts: Vec<String>, owner: String) -> async_graphql::Result<bool> {
+        if texts.iter().any(|text| text.trim().is_empty()) {
+            return Err(Error::new("nomination text must not be empty"));
+        }
+        let operation = Operation::NominateBatch { texts, owner };
+        self.schedule(operation)
+    }
+
+    /// Let another participant's ballot count for you if you cast none.
+    async fn delegate(&self, to: String, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::Delegate { to, owner };
+        self.schedule(operation)
+    }
+
+    /// Withdraw your ranked ballot while the poll is open.
+    async fn retract_vote(&self, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::RetractVote { owner };
+        self.schedule(operation)
+    }
+
+    /// Reword your own nomination before voting starts.
+    async fn edit_nomination(
+        &self,
+        nomination_id: String,
+        new_text: String,
+        owner: String,
+    ) -> async_graphql::Result<bool> {
+        if new_text.trim().is_empty() {
+            return Err(Error::new("nomination text must not be empty"));
+        }
+        let operation = Operation::EditNomination { nomination_id, new_text, owner };
+        self.schedule(operation)
+    }
+
+    /// Submit vote rankings.
+    async fn vote(&self, rankings: Vec<String>, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::Vote { rankings, owner };
+        self.schedule(operation)
+    }
+
+    /// Submit cumulative-voting point allocations.
+    async fn vote_points(&self, allocations: Vec<PointAllocation>, owner: String) -> async_graphql::Result<bool> {
+        let allocations = allocations
+            .into_iter()
+            .map(|allocation| (allocation.nomination_id, allocation.points))
+            .collect();
+        let operation = Operation::VotePoints { allocations, owner };
+        self.schedule(operation)
+    }
+
+    /// Rate nominations with 1 to 5 stars each.
+    async fn rate_options(&self, ratings: Vec<StarRating>, owner: String) -> async_graphql::Result<bool> {
+        let ratings = ratings.into_iter().map(|rating| (rating.nomination_id, rating.stars)).collect();
+        let operation = Operation::RateOptions { ratings, owner };
+        self.schedule(operation)
+    }
+
+    /// Publicly endorse a nomination.
+    async fn endorse_nomination(&self, nomination_id: String, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::EndorseNomination { nomination_id, owner };
+        self.schedule(operation)
+    }
+
+    /// Comment on a nomination.
+    async fn comment_on_nomination(&self, nomination_id: String, text: String, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::CommentOnNomination { nomination_id, text, owner };
+        self.schedule(operation)
+    }
+
+    /// Start the voting phase (admin only).
+    async fn start_vote(&self, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::StartVote { owner };
+        self.schedule(operation)
+    }
+
+    /// Close the poll and compute results (admin only).
+    async fn close_poll(&self, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::ClosePoll { owner };
+        self.schedule(operation)
+    }
+
+    /// Reopen a closed poll for voting, discarding its results (admin only).
+    async fn reopen_poll(&self, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::ReopenPoll { owner };
+        self.schedule(operation)
+    }
+
+    /// Freeze a closed poll's results for good (admin only).
+    async fn finalize_results(&self, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::FinalizeResults { owner };
+        self.schedule(operation)
+    }
+
+    /// Change the number of votes per voter (admin only).
+    async fn set_votes_per_voter(&self, votes_per_voter: u32, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::SetVotesPerVoter { votes_per_voter, owner };
+        self.schedule(operation)
+    }
+
+    /// Re-draw the tie-break for tied results (admin only).
+    async fn reroll_tie_break(&self, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::RerollTieBreak { owner };
+        self.schedule(operation)
+    }
+
+    /// Register the payout account for one of your nominations.
+    async fn set_payout_address(&self, nomination_id: String, address: Account, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::SetPayoutAddress { nomination_id, address, owner };
+        self.schedule(operation)
+    }
+
+    /// Remove a participant with their nominations and ballots, before voting starts (admin only).
+    async fn remove_participant(&self, target: String, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::RemoveParticipant { target, owner };
+        self.schedule(operation)
+    }
+
+    /// Abort the poll without computing results (admin only).
+    async fn cancel_poll(&self, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::CancelPoll { owner };
+        self.schedule(operation)
+    }
+
+    /// Change the caller's display name (participants only).
+    async fn rename(&self, name: String, owner: String) -> async_graphql::Result<bool> {
+        if name.trim().is_empty() {
+            return Err(Error::new("name must not be empty"));
+        }
+        let operation = Operation::Rename { name, owner };
+        self.schedule(operation)
+    }
+
+    /// Hand the admin role to another participant (admin only).
+    async fn transfer_admin(&self, new_admin: String, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::TransferAdmin { new_admin, owner };
+        self.schedule(operation)
+    }
+
+    /// Go back to the nomination phase, discarding all ballots (admin only).
+    async fn reopen_nominations(&self, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::ReopenNominations { owner };
+        self.schedule(operation)
+    }
+
+    /// Temporarily freeze all non-admin activity (admin only).
+    async fn pause_poll(&self, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::PausePoll { owner };
+        self.schedule(operation)
+    }
+
+    /// Lift a pause (admin only).
+    async fn resume_poll(&self, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::ResumePoll { owner };
+        self.schedule(operation)
+    }
+
+    /// Open a new poll seeded with the topic and nominations of the poll on `source_chain`.
+    async fn clone_poll(&self, source_chain: ChainId, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::ClonePoll { source_chain, owner };
+        self.schedule(operation)
+    }
+
+    /// Ask poll chains to report their results here for `aggregateResults`.
+    async fn collect_results(&self, chain_ids: Vec<ChainId>, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::CollectResults { chain_ids, owner };
+        self.schedule(operation)
+    }
+
+    /// Send this closed shard's results to a coordinator chain (admin only).
+    async fn submit_partial_results(&self, coordinator: ChainId, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::SubmitPartialResults { coordinator, owner };
+        self.schedule(operation)
+    }
+
+    /// Accept partial results from a shard chain on this coordinator (admin only).
+    async fn register_shard(&self, shard: ChainId, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::RegisterShard { shard, owner };
+        self.schedule(operation)
+    }
+
+    /// Toggle whether participants may join after voting has started (admin only).
+    async fn allow_late_join(&self, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::AllowLateJoin { owner };
+        self.schedule(operation)
+    }
+
+    /// Restrict voting to a subset of the nominations; an empty list lifts the restriction (admin only).
+    async fn set_shortlist(&self, nomination_ids: Vec<String>, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::SetShortlist { nomination_ids, owner };
+        self.schedule(operation)
+    }
+
+    /// Give a nomination a head start added to its tally (admin only).
+    async fn set_base_score(&self, nomination_id: String, base_score: u64, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::SetBaseScore { nomination_id, base_score, owner };
+        self.schedule(operation)
+    }
+
+    /// Set a participant's voting weight, multiplying their ranked-ballot points (admin only).
+    async fn set_participant_weight(&self, user_id: String, weight: u64, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::SetParticipantWeight { user_id, weight, owner };
+        self.schedule(operation)
+    }
+
+    /// Pay the chain's remaining balance to the winner (admin only).
+    async fn payout_winner(&self, owner: String) -> async_graphql::Result<bool> {
+        let operation = Operation::PayoutWinner { owner };
+        self.schedule(operation)
+    }
+}
+
+impl MutationRoot {
+    /// Schedule `operation`, refusing it up front when no owner is given: the contract would
+    /// only reject it once the block executes.
+    fn schedule(&self, operation: Operation) -> async_graphql::Result<bool> {
+        if operation.owner().trim().is_empty() {
+            return Err(Error::new("owner must not be empty"));
+        }
+        self.runtime.schedule_operation(&operation);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_graphql::{EmptyMutation, EmptySubscription, Response, Schema};
+    use linera_sdk::{
+        linera_base_types::{ChainId, CryptoHash, Timestamp},
+        util::BlockingWait,
+        views::View,
+        ServiceRuntime,
+    };
+    use meal_voting::{BordaVariant, Operation, TallyMethod};
+    use serde_json::Value;
+
+    use super::{
+        state::{AuditEntry, Nomination, ResultEntry},
+        MealVotingService, MutationRoot, PollState, QueryRoot,
+    };
+
+    fn default_chain_id() -> String {
+        ServiceRuntime::<MealVotingService>::new().chain_id().to_string()
+    }
+
+    fn query(setup: impl FnOnce(&mut PollState), query: &str) -> Value {
+        query_with_runtime(ServiceRuntime::new(), setup, query)
+    }
+
+    fn query_with_runtime(
+        runtime: ServiceRuntime<MealVotingService>,
+        setup: impl FnOnce(&mut PollState),
+        query: &str,
+    ) -> Value {
+        let mut state = PollState::load(runtime.root_view_storage_context())
+            .blocking_wait()
+            .expect("Failed to read from mock key value store");
+        setup(&mut state);
+        let schema = Schema::build(
+            QueryRoot { state: Arc::new(state), runtime: Arc::new(runtime) },
+            EmptyMutation,
+            EmptySubscription,
+        )
+        .finish();
+        let response = schema.execute(query).blocking_wait();
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        response.data.into_json().expect("Response should be valid JSON")
+    }
+
+    /// Run `mutation` against a fresh runtime, returning the response and the scheduled operations.
+    fn mutate(mutation: &str) -> (Response, Vec<Operation>) {
+        let runtime = Arc::new(ServiceRuntime::<MealVotingService>::new());
+        let state = PollState::load(runtime.root_view_storage_context())
+            .blocking_wait()
+            .expect("Failed to read from mock key value store");
+        let schema = Schema::build(
+            QueryRoot { state: Arc::new(state), runtime: runtime.clone() },
+            MutationRoot { runtime: runtime.clone() },
+            EmptySubscription,
+        )
+        .finish();
+        let response = schema.execute(mutation).blocking_wait();
+        (response, runtime.scheduled_operations())
+    }
+
+    fn results(scores: &[u64]) -> Vec<ResultEntry> {
+        scores
+            .iter()
+            .enumerate()
+            .map(|(i, score)| ResultEntry {
+                nomination_id: format!("nom_{}", i),
+                nomination_text: format!("Option {}", i),
+                score: *score,
+                eliminated_in_round: None,
+                first_choice_votes: 0,
+                elected: false,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn inequality_index_is_higher_for_dominated_results() {
+        let dominated = query(|state| state.results.set(results(&[30, 1, 1, 0])), "{ inequalityIndex }");
+        let even = query(|state| state.results.set(results(&[8, 8, 8, 8])), "{ inequalityIndex }");
+
+        let dominated_index = dominated["inequalityIndex"].as_f64().unwrap();
+        let even_index = even["inequalityIndex"].as_f64().unwrap();
+
+        assert!(even_index.abs() < 1e-9);
+        assert!(dominated_index > 0.6);
+        assert!(dominated_index > even_index);
+    }
+
+    #[test]
+    fn bullet_vote_count_counts_single_choice_ballots() {
+        let data = query(
+            |state| {
+                state.votes_per_voter.set(3);
+                for (id, text) in [("nom_0", "Pizza"), ("nom_1", "Sushi"), ("nom_2", "Tacos")] {
+                    let nomination = Nomination { user_id: "alice".to_string(), text: text.to_string(), base_score: 0, category: None, tags: Vec::new() };
+                    state.nominations.insert(id, nomination).unwrap();
+                }
+                state.rankings.insert("alice", vec!["nom_0".to_string()]).unwrap();
+                state.rankings.insert("bob", vec!["nom_1".to_string(), "nom_0".to_string()]).unwrap();
+                state.rankings.insert("carol", vec!["nom_2".to_string()]).unwrap();
+            },
+            "{ bulletVoteCount }",
+        );
+
+        assert_eq!(data["bulletVoteCount"], 2);
+    }
+
+    #[test]
+    fn fallback_queries_return_lower_places() {
+        let data = query(
+            |state| state.results.set(results(&[9, 5, 2])),
+            "{ fallbackWinner { nominationId } third: fallbackChain(n: 3) { nominationId } missing: fallbackChain(n: 4) { nominationId } }",
+        );
+
+        assert_eq!(data["fallbackWinner"]["nominationId"], "nom_1");
+        assert_eq!(data["third"]["nominationId"], "nom_2");
+        assert!(data["missing"].is_null());
+    }
+
+    #[test]
+    fn nominations_include_endorsement_counts() {
+        let data = query(
+            |state| {
+                for (id, text) in [("nom_0", "Pizza"), ("nom_1", "Sushi")] {
+                    let nomination = Nomination { user_id: "alice".to_string(), text: text.to_string(), base_score: 0, category: None, tags: Vec::new() };
+                    state.nominations.insert(id, nomination).unwrap();
+                }
+                state.endorsements.insert("nom_1", vec!["alice".to_string(), "bob".to_string()]).unwrap();
+            },
+            "{ nominations { nominationId endorsementCount } }",
+        );
+
+        assert_eq!(data["nominations"][0]["endorsementCount"], 0);
+        assert_eq!(data["nominations"][1]["endorsementCount"], 2);
+    }
+
+    #[test]
+    fn share_link_contains_chain_id_and_status() {
+        let data = query(
+            |state| {
+                state.topic.set("Friday lunch".to_string());
+                state.has_started.set(true);
+            },
+            "{ shareLink { chainId topic status invite } }",
+        );
+
+        let chain_id = default_chain_id();
+        assert_eq!(data["shareLink"]["chainId"], chain_id.as_str());
+        assert_eq!(data["shareLink"]["topic"], "Friday lunch");
+        assert_eq!(data["shareLink"]["status"], "VOTING");
+        assert!(data["shareLink"]["invite"].as_str().unwrap().contains(&chain_id));
+    }
+
+    fn set_ranked_ballots(state: &mut PollState, ballots: &[(&str, &[&str])]) {
+        for (user_id, ballot) in ballots {
+            let ballot = ballot.iter().map(|id| id.to_string()).collect();
+            state.rankings.insert(*user_id, ballot).unwrap();
+        }
+    }
+
+    #[test]
+    fn flip_margin_is_smaller_for_near_ties_than_landslides() {
+        let near_tie = query(
+            |state| {
+                state.votes_per_voter.set(2);
+                set_ranked_ballots(state, &[("a", &["nom_0", "nom_1"]), ("b", &["nom_1", "nom_0"]), ("c", &["nom_0"])]);
+                state.results.set(results(&[5, 3]));
+            },
+            "{ flipMargin }",
+        );
+        let landslide = query(
+            |state| {
+                state.votes_per_voter.set(2);
+                let voters = ["a", "b", "c", "d", "e", "f"];
+                let ballots: Vec<(&str, &[&str])> = voters.iter().map(|voter| (*voter, &["nom_0"][..])).collect();
+                set_ranked_ballots(state, &ballots);
+                state.results.set(results(&[12, 0]));
+            },
+            "{ flipMargin }",
+        );
+
+        assert_eq!(near_tie["flipMargin"], 1);
+        assert_eq!(landslide["flipMargin"], 4);
+    }
+
+    #[test]
+    fn flip_coalition_follows_the_borda_variant_and_weights() {
+        let data = query(
+            |state| {
+                state.votes_per_voter.set(2);
+                state.borda_variant.set(BordaVariant::Dowdall);
+                state.participant_weights.insert("a", 3).unwrap();
+                set_ranked_ballots(
+                    state,
+                    &[("a", &["nom_0", "nom_1"]), ("b", &["nom_1", "nom_0"]), ("c", &["nom_1", "nom_0"])],
+                );
+                // nom_0: 3 * 2520 + 1260 + 1260 = 10080, nom_1: 3 * 1260 + 2520 + 2520 = 8820.
+                state.results.set(results(&[10_080, 8_820]));
+            },
+            "{ minimalFlipCoalition flipMargin }",
+        );
+
+        // Only "a" swings more than the 1260 lead: 3 * (2520 + 2520 - 1260).
+        assert_eq!(data["minimalFlipCoalition"], serde_json::json!(["a"]));
+        assert_eq!(data["flipMargin"], 1);
+    }
+
+    #[test]
+    fn status_is_scheduled_until_opens_at() {
+        let setup = |state: &mut PollState| state.opens_at.set(Some(Timestamp::from(1_000)));
+        let before = query_with_runtime(ServiceRuntime::new().with_system_time(Timestamp::from(999)), setup, "{ status }");
+        let after = query_with_runtime(ServiceRuntime::new().with_system_time(Timestamp::from(1_000)), setup, "{ status }");
+
+        assert_eq!(before["status"], "SCHEDULED");
+        assert_eq!(after["status"], "NOMINATING");
+    }
+
+    #[test]
+    fn inactive_participants_excludes_nominators_and_voters() {
+        let data = query(
+            |state| {
+                for user_id in ["alice", "bob", "carol", "dave"] {
+                    state.participants.insert(user_id, user_id.to_uppercase()).unwrap();
+                }
+                let nomination = Nomination { user_id: "alice".to_string(), text: "Pizza".to_string(), base_score: 0, category: None, tags: Vec::new() };
+                state.nominations.insert("nom_0", nomination).unwrap();
+                set_ranked_ballots(state, &[("bob", &["nom_0"])]);
+            },
+            "{ inactiveParticipants { userId name } }",
+        );
+        let rated = query(
+            |state| {
+                state.participants.insert("erin", "ERIN".to_string()).unwrap();
+                state.star_ratings.insert("erin", vec![("nom_0".to_string(), 4)]).unwrap();
+                state.star_ratings.insert("frank", vec![("nom_0".to_string(), 2)]).unwrap();
+            },
+            "{ inactiveParticipants { userId } checkInvariants }",
+        );
+
+        let inactive: Vec<_> = data["inactiveParticipants"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["userId"].as_str().unwrap())
+            .collect();
+        assert_eq!(inactive, vec!["dave", "carol"]);
+        assert_eq!(rated["inactiveParticipants"], serde_json::json!([]));
+        assert_eq!(rated["checkInvariants"], serde_json::json!(["Ratings given by non-participant frank"]));
+    }
+
+    #[test]
+    fn results_attestation_hash_is_stable_and_tracks_results() {
+        let closed = |scores: &'static [u64]| {
+            move |state: &mut PollState| {
+                state.admin_id.set("admin".to_string());
+                state.closed_at.set(Some(Timestamp::from(5_000)));
+                state.results.set(results(scores));
+            }
+        };
+        let hash_of = |data: Value| data["resultsAttestation"]["hash"].as_str().unwrap().to_string();
+
+        let first = hash_of(query(closed(&[4, 2]), "{ resultsAttestation { hash } }"));
+        let second = hash_of(query(closed(&[4, 2]), "{ resultsAttestation { hash } }"));
+        let different = hash_of(query(closed(&[4, 3]), "{ resultsAttestation { hash } }"));
+
+        assert_eq!(first.len(), 64);
+        assert_eq!(first, second);
+        assert_ne!(first, different);
+    }
+
+    #[test]
+    fn ballot_similarity_is_one_for_identical_and_minus_one_for_reversed_ballots() {
+        let data = query(
+            |state| {
+                set_ranked_ballots(
+                    state,
+                    &[
+                        ("alice", &["nom_0", "nom_1", "nom_2"]),
+                        ("bob", &["nom_0", "nom_1", "nom_2"]),
+                        ("carol", &["nom_2", "nom_1", "nom_0"]),
+                    ],
+                );
+            },
+            r#"{ same: ballotSimilarity(a: "alice", b: "bob") reversed: ballotSimilarity(a: "alice", b: "carol") }"#,
+        );
+
+        assert_eq!(data["same"], 1.0);
+        assert_eq!(data["reversed"], -1.0);
+    }
+
+    #[test]
+    fn kemeny_ranking_minimizes_pairwise_disagreement() {
+        let data = query(
+            |state| {
+                for (i, text) in ["Pizza", "Sushi", "Tacos"].into_iter().enumerate() {
+                    let nomination = Nomination { user_id: "alice".to_string(), text: text.to_string(), base_score: 0, category: None, tags: Vec::new() };
+                    state.nominations.insert(&format!("nom_{}", i), nomination).unwrap();
+                }
+                set_ranked_ballots(
+                    state,
+                    &[
+                        ("v1", &["nom_1", "nom_2", "nom_0"]),
+                        ("v2", &["nom_1", "nom_2", "nom_0"]),
+                        ("v3", &["nom_1", "nom_2", "nom_0"]),
+                        ("v4", &["nom_0", "nom_2", "nom_1"]),
+                        ("v5", &["nom_0", "nom_2", "nom_1"]),
+                    ],
+                );
+            },
+            "{ kemenyRanking }",
+        );
+
+        assert_eq!(data["kemenyRanking"], serde_json::json!(["nom_1", "nom_2", "nom_0"]));
+    }
+
+    #[test]
+    fn result_tiers_split_at_the_largest_score_gaps() {
+        let data = query(
+            |state| state.results.set(results(&[20, 19, 10, 9, 1])),
+            "{ resultTiers(tiers: 2) { score } }",
+        );
+
+        assert_eq!(
+            data["resultTiers"],
+            serde_json::json!([[{ "score": 20 }, { "score": 19 }], [{ "score": 10 }, { "score": 9 }, { "score": 1 }]])
+        );
+    }
+
+    #[test]
+    fn top_k_appearances_counts_ballots_ranking_the_option_within_k() {
+        let data = query(
+            |state| {
+                set_ranked_ballots(
+                    state,
+                    &[("a", &["nom_0", "nom_1"]), ("b", &["nom_1", "nom_0"]), ("c", &["nom_1"]), ("d", &["nom_2", "nom_1"])],
+                );
+            },
+            r#"{ top1: topKAppearances(nominationId: "nom_0", k: 1) top2: topKAppearances(nominationId: "nom_0", k: 2) }"#,
+        );
+
+        assert_eq!(data["top1"], 1);
+        assert_eq!(data["top2"], 2);
+    }
+
+    #[test]
+    fn meta_returns_register_values() {
+        let data = query(
+            |state| {
+                state.topic.set("Friday lunch".to_string());
+                state.admin_id.set("admin".to_string());
+                state.votes_per_voter.set(3);
+                state.has_started.set(true);
+                state.ballots_final.set(true);
+                state.quorum_weight.set(Some(4));
+            },
+            "{ meta { topic adminId votesPerVoter hasStarted isClosed ballotsFinal quorumWeight } }",
+        );
+
+        assert_eq!(
+            data["meta"],
+            serde_json::json!({
+                "topic": "Friday lunch",
+                "adminId": "admin",
+                "votesPerVoter": 3,
+                "hasStarted": true,
+                "isClosed": false,
+                "ballotsFinal": true,
+                "quorumWeight": 4,
+            })
+        );
+    }
+
+    #[test]
+    fn minimal_flip_coalition_names_the_largest_swing_voters() {
+        let data = query(
+            |state| {
+                state.votes_per_voter.set(2);
+                set_ranked_ballots(
+                    state,
+                    &[("a", &["nom_0", "nom_1"]), ("b", &["nom_0"]), ("c", &["nom_0", "nom_1"]), ("d", &["nom_1", "nom_0"])],
+                );
+                // nom_0: 2 + 2 + 2 + 1 = 7, nom_1: 1 + 0 + 1 + 2 = 4.
+                state.results.set(results(&[7, 4]));
+            },
+            "{ minimalFlipCoalition flipMargin }",
+        );
+
+        assert_eq!(data["minimalFlipCoalition"], serde_json::json!(["b"]));
+        assert_eq!(data["flipMargin"], 1);
+    }
+
+    #[test]
+    fn voter_influence_flags_the_pivotal_voter() {
+        let data = query(
+            |state| {
+                state.votes_per_voter.set(3);
+                // nom_0: 3 + 2 = 5, nom_1: 3. Without "a", nom_1 wins 3 to 2.
+                set_ranked_ballots(state, &[("a", &["nom_0"]), ("b", &["nom_1", "nom_0"])]);
+            },
+            "{ voterInfluence { userId influence pivotal } }",
+        );
+
+        assert_eq!(
+            data["voterInfluence"],
+            serde_json::json!([
+                { "userId": "a", "influence": 3, "pivotal": true },
+                { "userId": "b", "influence": -1, "pivotal": false },
+            ])
+        );
+    }
+
+    #[test]
+    fn voter_influence_counts_delegated_ballots() {
+        let data = query(
+            |state| {
+                state.votes_per_voter.set(3);
+                // "c" inherits "a"'s ballot: nom_0: 3 + 2 + 3 = 8, nom_1: 3.
+                set_ranked_ballots(state, &[("a", &["nom_0"]), ("b", &["nom_1", "nom_0"])]);
+                state.delegations.insert("c", "a".to_string()).unwrap();
+            },
+            "{ voterInfluence { userId influence pivotal } }",
+        );
+
+        assert_eq!(
+            data["voterInfluence"],
+            serde_json::json!([
+                { "userId": "a", "influence": 3, "pivotal": false },
+                { "userId": "c", "influence": 3, "pivotal": false },
+                { "userId": "b", "influence": -1, "pivotal": false },
+            ])
+        );
+    }
+
+    #[test]
+    fn voter_regret_measures_the_winner_position_on_each_ballot() {
+        let data = query(
+            |state| {
+                set_ranked_ballots(
+                    state,
+                    &[("a", &["nom_0", "nom_1"]), ("b", &["nom_1", "nom_0"]), ("c", &["nom_1", "nom_2"])],
+                );
+                state.results.set(results(&[5, 3, 1]));
+            },
+            "{ voterRegret { voters { userId regret } average } }",
+        );
+
+        assert_eq!(
+            data["voterRegret"]["voters"],
+            serde_json::json!([
+                { "userId": "a", "regret": 0 },
+                { "userId": "b", "regret": 1 },
+                { "userId": "c", "regret": 2 },
+            ])
+        );
+        assert_eq!(data["voterRegret"]["average"], 1.0);
+    }
+
+    #[test]
+    fn decision_overdue_flips_after_the_deadline() {
+        let setup = |state: &mut PollState| state.decision_deadline.set(Some(Timestamp::from(1_000)));
+        let at = query_with_runtime(ServiceRuntime::new().with_system_time(Timestamp::from(1_000)), setup, "{ decisionOverdue }");
+        let after = query_with_runtime(ServiceRuntime::new().with_system_time(Timestamp::from(1_001)), setup, "{ decisionOverdue }");
+
+        assert_eq!(at["decisionOverdue"], false);
+        assert_eq!(after["decisionOverdue"], true);
+    }
+
+    #[test]
+    fn aggregate_ballots_counts_nominations_per_position() {
+        let data = query(
+            |state| {
+                set_ranked_ballots(state, &[("a", &["nom_0", "nom_1"]), ("b", &["nom_1", "nom_0"]), ("c", &["nom_0"])]);
+            },
+            "{ aggregateBallots { position counts { nominationId count } } }",
+        );
+
+        assert_eq!(
+            data["aggregateBallots"],
+            serde_json::json!([
+                { "position": 0, "counts": [{ "nominationId": "nom_0", "count": 2 }, { "nominationId": "nom_1", "count": 1 }] },
+                { "position": 1, "counts": [{ "nominationId": "nom_0", "count": 1 }, { "nominationId": "nom_1", "count": 1 }] },
+            ])
+        );
+    }
+
+    #[test]
+    fn ranking_depth_stats_summarize_ballot_lengths() {
+        let data = query(
+            |state| {
+                set_ranked_ballots(
+                    state,
+                    &[
+                        ("a", &["nom_0"]),
+                        ("b", &["nom_0", "nom_1"]),
+                        ("c", &["nom_0", "nom_1", "nom_2"]),
+                        ("d", &["nom_0", "nom_1", "nom_2", "nom_3", "nom_4", "nom_5"]),
+                    ],
+                );
+            },
+            "{ rankingDepthStats { min max mean median } }",
+        );
+
+        assert_eq!(data["rankingDepthStats"], serde_json::json!({ "min": 1, "max": 6, "mean": 3.0, "median": 2.5 }));
+    }
+
+    #[test]
+    fn nominations_with_tag_returns_only_tagged_nominations() {
+        let data = query(
+            |state| {
+                for (i, tags) in [vec!["vegan", "gluten-free"], vec![], vec!["vegan"]].into_iter().enumerate() {
+                    let nomination = Nomination {
+                        user_id: "alice".to_string(),
+                        text: format!("Option {}", i),
+                        base_score: 0,
+                        category: None,
+                        tags: tags.into_iter().map(String::from).collect(),
+                    };
+                    state.nominations.insert(&format!("nom_{}", i), nomination).unwrap();
+                }
+            },
+            r#"{ nominationsWithTag(tag: " Vegan") { nominationId } }"#,
+        );
+
+        assert_eq!(
+            data["nominationsWithTag"],
+            serde_json::json!([{ "nominationId": "nom_0" }, { "nominationId": "nom_2" }])
+        );
+    }
+
+    #[test]
+    fn check_invariants_detects_a_dangling_ballot_id() {
+        let data = query(
+            |state| {
+                state.participants.insert("alice", "Alice".to_string()).unwrap();
+                let nomination = Nomination {
+                    user_id: "alice".to_string(),
+                    text: "Pizza".to_string(),
+                    base_score: 0,
+                    category: None,
+                    tags: Vec::new(),
+                };
+                state.nominations.insert("nom_0", nomination).unwrap();
+                state.next_nomination_id.set(1);
+                set_ranked_ballots(state, &[("alice", &["nom_0", "nom_7"])]);
+            },
+            "{ checkInvariants }",
+        );
+
+        assert_eq!(data["checkInvariants"], serde_json::json!(["Ballot of alice ranks unknown nomination nom_7"]));
+    }
+
+    #[test]
+    fn check_invariants_only_recounts_local_borda_results() {
+        let setup = |tally_method: TallyMethod| {
+            move |state: &mut PollState| {
+                state.tally_method.set(tally_method);
+                state.votes_per_voter.set(2);
+                state.participants.insert("alice", "Alice".to_string()).unwrap();
+                set_ranked_ballots(state, &[("alice", &[])]);
+                state.results.set(results(&[1]));
+            }
+        };
+        let borda = query(setup(TallyMethod::Borda), "{ checkInvariants }");
+        let runoff = query(setup(TallyMethod::InstantRunoff), "{ checkInvariants }");
+        let plurality = query(setup(TallyMethod::Plurality), "{ checkInvariants }");
+        let merged = query(
+            |state| {
+                setup(TallyMethod::Borda)(state);
+                let shard = ChainId(CryptoHash::test_hash("shard"));
+                state.partial_results.insert(&shard, results(&[1])).unwrap();
+            },
+            "{ checkInvariants }",
+        );
+
+        assert_eq!(borda["checkInvariants"], serde_json::json!(["Stored results do not match a fresh tally"]));
+        assert_eq!(runoff["checkInvariants"], serde_json::json!([]));
+        assert_eq!(plurality["checkInvariants"], serde_json::json!([]));
+        assert_eq!(merged["checkInvariants"], serde_json::json!([]));
+    }
+
+    fn audit_entries(state: &mut PollState, entries: &[(&str, &str)]) {
+        for (i, (operation_type, user_id)) in entries.iter().enumerate() {
+            state.audit_log.push(AuditEntry {
+                operation_type: operation_type.to_string(),
+                user_id: user_id.to_string(),
+                timestamp: Timestamp::from(i as u64),
+            });
+        }
+    }
+
+    #[test]
+    fn audit_log_pages_through_entries() {
+        let data = query(
+            |state| audit_entries(state, &[("Join", "a"), ("Join", "b"), ("Nominate", "a"), ("Vote", "b")]),
+            "{ page: auditLog(offset: 1, limit: 2) { operationType userId } tail: auditLog(offset: 3) { userId } }",
+        );
+
+        assert_eq!(
+            data["page"],
+            serde_json::json!([{ "operationType": "Join", "userId": "b" }, { "operationType": "Nominate", "userId": "a" }])
+        );
+        assert_eq!(data["tail"], serde_json::json!([{ "userId": "b" }]));
+    }
+
+    #[test]
+    fn audit_log_filters_by_operation_type() {
+        let data = query(
+            |state| audit_entries(state, &[("Join", "a"), ("Vote", "a"), ("Join", "b"), ("Vote", "b"), ("Vote", "c")]),
+            r#"{ auditLog(operationType: "Vote", offset: 1) { userId } }"#,
+        );
+
+        assert_eq!(data["auditLog"], serde_json::json!([{ "userId": "b" }, { "userId": "c" }]));
+    }
+
+    #[test]
+    fn aggregate_results_combines_matching_nominations_across_chains() {
+        let team_a = ChainId(CryptoHash::test_hash("team a"));
+        let team_b = ChainId(CryptoHash::test_hash("team b"));
+        let entry = |text: &str, score| ResultEntry {
+            nomination_id: "nom_0".to_string(),
+            nomination_text: text.to_string(),
+            score,
+            eliminated_in_round: None,
+            first_choice_votes: 0,
+            elected: false,
+        };
+        let data = query(
+            |state| {
+                state.poll_results.insert(&team_a, vec![entry("Pizza", 5), entry("Sushi", 4)]).unwrap();
+                state.poll_results.insert(&team_b, vec![entry("sushi ", 6), entry("Tacos", 2)]).unwrap();
+            },
+            &format!(
+                r#"{{ aggregateResults(chainIds: ["{}", "{}"]) {{ nominationId nominationText score }} }}"#,
+                team_a, team_b
+            ),
+        );
+
+        assert_eq!(
+            data["aggregateResults"],
+            serde_json::json!([
+                { "nominationId": "sushi", "nominationText": "Sushi", "score": 10 },
+                { "nominationId": "pizza", "nominationText": "Pizza", "score": 5 },
+                { "nominationId": "tacos", "nominationText": "Tacos", "score": 2 },
+            ])
+        );
+    }
+
+    #[test]
+    fn winner_supporters_lists_voters_ranking_the_winner_highly() {
+        let data = query(
+            |state| {
+                set_ranked_ballots(
+                    state,
+                    &[("a", &["nom_0", "nom_1"]), ("b", &["nom_1", "nom_0"]), ("c", &["nom_0"]), ("d", &["nom_1"])],
+                );
+                state.results.set(results(&[5, 4]));
+            },
+            "{ first: winnerSupporters topTwo: winnerSupporters(k: 2) }",
+        );
+
+        assert_eq!(data["first"], serde_json::json!(["a", "c"]));
+        assert_eq!(data["topTwo"], serde_json::json!(["a", "b", "c"]));
+    }
+
+    #[test]
+    fn winner_queries_report_the_top_results_after_close() {
+        let request = "{ winner { nominationId } winners(n: 2) { nominationId } tiedWinners { nominationId } }";
+        let open = query(|state| state.results.set(results(&[7, 7, 3])), request);
+        assert_eq!(open["winner"], serde_json::Value::Null);
+        assert_eq!(open["tiedWinners"], serde_json::json!([]));
+
+        let closed = query(
+            |state| {
+                state.results.set(results(&[7, 7, 3]));
+                state.is_closed.set(true);
+            },
+            request,
+        );
+        let ids = |value: &serde_json::Value| {
+            value.as_array().unwrap().iter().map(|entry| entry["nominationId"].clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(closed["winner"]["nominationId"], "nom_0");
+        assert_eq!(ids(&closed["winners"]), vec!["nom_0", "nom_1"]);
+        assert_eq!(ids(&closed["tiedWinners"]), vec!["nom_0", "nom_1"]);
+    }
+
+    #[test]
+    fn nomination_looks_up_a_single_entry() {
+        let data = query(
+            |state| {
+                let nomination = Nomination {
+                    user_id: "alice".to_string(),
+                    text: "Pizza".to_string(),
+                    base_score: 0,
+                    category: None,
+                    tags: Vec::new(),
+                };
+                state.nominations.insert("nom_0", nomination).unwrap();
+            },
+            r#"{ present: nomination(nominationId: "nom_0") { text userId } missing: nomination(nominationId: "nom_9") { text } }"#,
+        );
+
+        assert_eq!(data["present"], serde_json::json!({ "text": "Pizza", "userId": "alice" }));
+        assert_eq!(data["missing"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn participant_pages_cover_the_list_in_a_stable_order() {
+        let setup = |state: &mut PollState| {
+            for name in ["erin", "alice", "dave", "bob", "carol"] {
+                state.participants.insert(name, name.to_uppercase()).unwrap();
+            }
+        };
+        let data = query(
+            setup,
+            "{ all: participants { userId } \
+               first: participants(limit: 2) { userId } \
+               second: participants(offset: 2, limit: 2) { userId } \
+               last: participants(offset: 4, limit: 2) { userId } \
+               beyond: participants(offset: 5) { userId } }",
+        );
+
+        let ids = |value: &serde_json::Value| {
+            value.as_array().unwrap().iter().map(|entry| entry["userId"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+        };
+        let pages: Vec<String> = ["first", "second", "last"].iter().flat_map(|page| ids(&data[*page])).collect();
+        assert_eq!(ids(&data["all"]), vec!["bob", "dave", "erin", "alice", "carol"]);
+        assert_eq!(pages, ids(&data["all"]));
+        assert_eq!(ids(&data["last"]), vec!["carol"]);
+        assert!(ids(&data["beyond"]).is_empty());
+    }
+
+    #[test]
+    fn results_export_is_deterministic() {
+        let setup = |state: &mut PollState| {
+            state.topic.set("Lunch".to_string());
+            state.rankings.insert("bob", vec!["nom_1".to_string(), "nom_0".to_string()]).unwrap();
+            state.rankings.insert("alice", vec!["nom_0".to_string()]).unwrap();
+            state.results.set(results(&[3, 2]));
+        };
+        let first = query(setup, "{ resultsExport }");
+        let second = query(setup, "{ resultsExport }");
+
+        assert_eq!(first, second);
+        let export: Value = serde_json::from_str(first["resultsExport"].as_str().unwrap()).unwrap();
+        assert_eq!(export["topic"], "Lunch");
+        assert_eq!(export["tally_method"], "Borda");
+        assert_eq!(export["ballots"]["bob"][0], "nom_1");
+        assert_eq!(export["results"][1]["score"], 2);
+    }
+
+    #[test]
+    fn phase_follows_the_poll_lifecycle() {
+        let nominating = query(|_| {}, "{ phase }");
+        let voting = query(|state| state.has_started.set(true), "{ phase }");
+        let closed = query(
+            |state| {
+                state.has_started.set(true);
+                state.is_closed.set(true);
+            },
+            "{ phase }",
+        );
+
+        assert_eq!(nominating["phase"], "NOMINATING");
+        assert_eq!(voting["phase"], "VOTING");
+        assert_eq!(closed["phase"], "CLOSED");
+    }
+
+    #[test]
+    fn hidden_ballots_are_revealed_after_close() {
+        let setup = |closed: bool| {
+            move |state: &mut PollState| {
+                state.hide_ballots_until_close.set(true);
+                state.has_started.set(true);
+                state.is_closed.set(closed);
+                state.rankings.insert("alice", vec!["nom_0".to_string()]).unwrap();
+                state.results.set(results(&[3]));
+            }
+        };
+        let mid_vote = query(setup(false), "{ rankings { userId } results { score } }");
+        let closed = query(setup(true), "{ rankings { userId } results { score } }");
+
+        assert_eq!(mid_vote["rankings"], serde_json::json!([]));
+        assert_eq!(mid_vote["results"], serde_json::json!([]));
+        assert_eq!(closed["rankings"][0]["userId"], "alice");
+        assert_eq!(closed["results"][0]["score"], 3);
+    }
+
+    #[test]
+    fn hidden_ballots_stay_out_of_analysis_queries_mid_vote() {
+        let setup = |closed: bool| {
+            move |state: &mut PollState| {
+                state.hide_ballots_until_close.set(true);
+                state.has_started.set(true);
+                state.is_closed.set(closed);
+                state.votes_per_voter.set(2);
+                set_ranked_ballots(state, &[("alice", &["nom_0"]), ("bob", &["nom_1", "nom_0"])]);
+            }
+        };
+        let analysis = "{ resultsExport voterInfluence { userId } aggregateBallots { position } \
+            topKAppearances(nominationId: \"nom_0\", k: 1) bulletVoteCount rankingDepthStats { max } \
+            ballotSimilarity(a: \"alice\", b: \"bob\") }";
+        let mid_vote = query(setup(false), analysis);
+        let closed = query(setup(true), analysis);
+
+        let export: Value = serde_json::from_str(mid_vote["resultsExport"].as_str().unwrap()).unwrap();
+        assert_eq!(export["ballots"], serde_json::json!({}));
+        assert_eq!(mid_vote["voterInfluence"], serde_json::json!([]));
+        assert_eq!(mid_vote["aggregateBallots"], serde_json::json!([]));
+        assert_eq!(mid_vote["topKAppearances"], 0);
+        assert_eq!(mid_vote["bulletVoteCount"], 0);
+        assert!(mid_vote["rankingDepthStats"].is_null());
+        assert_eq!(mid_vote["ballotSimilarity"], 0.0);
+
+        let export: Value = serde_json::from_str(closed["resultsExport"].as_str().unwrap()).unwrap();
+        assert_eq!(export["ballots"]["alice"], serde_json::json!(["nom_0"]));
+        assert_eq!(closed["voterInfluence"].as_array().unwrap().len(), 2);
+        assert_eq!(closed["topKAppearances"], 1);
+        assert_eq!(closed["rankingDepthStats"]["max"], 2);
+    }
+
+    #[test]
+    fn mutations_reject_blank_input_without_scheduling() {
+        for mutation in [
+            r#"mutation { createPoll(topic: " ", votesPerVoter: 2, owner: "alice") }"#,
+            r#"mutation { createPoll(topic: "Lunch", votesPerVoter: 0, owner: "alice") }"#,
+            r#"mutation { join(name: "", owner: "alice") }"#,
+            r#"mutation { startVote(owner: "") }"#,
+        ] {
+            let (response, scheduled) = mutate(mutation);
+            assert_eq!(response.errors.len(), 1, "{}", mutation);
+            assert!(scheduled.is_empty(), "{}", mutation);
+        }
+
+        let (response, scheduled) = mutate(r#"mutation { createPoll(topic: "Lunch", votesPerVoter: 2, owner: "alice") }"#);
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert!(matches!(&scheduled[..], [Operation::CreatePoll { topic, .. }] if topic == "Lunch"));
+    }
+
+    #[test]
+    fn nomination_results_rank_ties_together() {
+        let data = query(
+            |state| {
+                for i in 0..3 {
+                    let nomination = Nomination { user_id: format!("user_{}", i), text: format!("Option {}", i), base_score: 0, category: None, tags: Vec::new() };
+                    state.nominations.insert(&format!("nom_{}", i), nomination).unwrap();
+                }
+                state.results.set(results(&[7, 4, 4]));
+            },
+            "{ nominationResults { nominationId userId score rank } }",
+        );
+
+        let entries = &data["nominationResults"];
+        assert_eq!(entries[0]["rank"], 1);
+        assert_eq!(entries[0]["userId"], "user_0");
+        assert_eq!(entries[0]["score"], 7);
+        assert_eq!(entries[1]["rank"], 2);
+        assert_eq!(entries[2]["rank"], 2);
+    }
+
+    #[test]
+    fn health_reports_version_and_poll_figures() {
+        let data = query(
+            |state| {
+                state.has_started.set(true);
+                state.tally_method.set(TallyMethod::Approval);
+                state.participants.insert("alice", "Alice".to_string()).unwrap();
+                state.participants.insert("bob", "Bob".to_string()).unwrap();
+                let nomination = Nomination { user_id: "alice".to_string(), text: "Pizza".to_string(), base_score: 0, category: None, tags: Vec::new() };
+                state.nominations.insert("nom_0", nomination).unwrap();
+            },
+            "{ health { version tallyMethod phase participantCount nominationCount } }",
+        );
+
+        let health = &data["health"];
+        assert!(!health["version"].as_str().unwrap().is_empty());
+        assert_eq!(health["tallyMethod"], "APPROVAL");
+        assert_eq!(health["phase"], "VOTING");
+        assert_eq!(health["participantCount"], 2);
+        assert_eq!(health["nominationCount"], 1);
+    }
+
+    #[test]
+    fn turnout_counts_voters_against_participants() {
+        let data = query(
+            |state| {
+                for name in ["alice", "bob", "carol"] {
+                    state.participants.insert(name, name.to_string()).unwrap();
+                }
+                state.rankings.insert("alice", vec!["nom_0".to_string()]).unwrap();
+                state.rankings.insert("bob", Vec::new()).unwrap();
+                state.abstentions.set(1);
+            },
+            "{ turnout { eligible voted abstained percentage } }",
+        );
+
+        let turnout = &data["turnout"];
+        assert_eq!(turnout["eligible"], 3);
+        assert_eq!(turnout["voted"], 2);
+        assert_eq!(turnout["abstained"], 1);
+        assert_eq!(turnout["percentage"].as_f64().unwrap().floor(), 66.0);
+    }
+
+    #[test]
+    fn poll_results_returns_a_chain_report() {
+        let poll_chain = ChainId(CryptoHash::test_hash("poll"));
+        let data = query(
+            |state| state.poll_results.insert(&poll_chain, results(&[4, 1])).unwrap(),
+            &format!("{{ pollResults(chainId: \"{}\") {{ nominationId score }} }}", poll_chain),
+        );
+
+        assert_eq!(data["pollResults"][0]["nominationId"], "nom_0");
+        assert_eq!(data["pollResults"][1]["score"], 1);
+    }
+
+    #[test]
+    fn anonymous_ballots_hash_voter_ids_with_the_poll_salt() {
+        let setup = |anonymous: bool, salt: &'static str| {
+            move |state: &mut PollState| {
+                state.anonymous_ballots.set(anonymous);
+                state.ballot_salt.set(salt.to_string());
+                state.votes_per_voter.set(2);
+                state.rankings.insert("alice", vec!["nom_0".to_string()]).unwrap();
+                state.rankings.insert("carol", vec!["nom_1".to_string(), "nom_0".to_string()]).unwrap();
+                state.is_closed.set(true);
+                state.results.set(results(&[3, 2]));
+            }
+        };
+        let queries = "{ rankings { userId nominationIds } winnerSupporters resultsExport \
+            voterRegret { voters { userId } } voterInfluence { userId } ballotSimilarity(a: \"alice\", b: \"carol\") }";
+        let revealed = query(setup(false, "pepper"), queries);
+        let anonymous = query(setup(true, "pepper"), queries);
+        let again = query(setup(true, "pepper"), queries);
+        let resalted = query(setup(true, "paprika"), queries);
+
+        let label = |data: &Value, nomination_ids: Value| {
+            let rankings = data["rankings"].as_array().unwrap();
+            let entry = rankings.iter().find(|entry| entry["nominationIds"] == nomination_ids).unwrap();
+            entry["userId"].as_str().unwrap().to_string()
+        };
+        assert_eq!(label(&revealed, serde_json::json!(["nom_0"])), "alice");
+        assert_eq!(revealed["winnerSupporters"], serde_json::json!(["alice"]));
+        let alice = label(&anonymous, serde_json::json!(["nom_0"]));
+        let carol = label(&anonymous, serde_json::json!(["nom_1", "nom_0"]));
+        assert_eq!(alice.len(), 64);
+        assert_ne!(alice, carol);
+        assert_eq!(label(&again, serde_json::json!(["nom_0"])), alice);
+        assert_ne!(label(&resalted, serde_json::json!(["nom_0"])), alice);
+
+        assert_eq!(anonymous["winnerSupporters"], serde_json::json!([alice]));
+        let export: Value = serde_json::from_str(anonymous["resultsExport"].as_str().unwrap()).unwrap();
+        assert_eq!(export["ballots"][&alice], serde_json::json!(["nom_0"]));
+        assert!(export["ballots"].get("alice").is_none());
+        for data in [&anonymous["voterRegret"]["voters"], &anonymous["voterInfluence"]] {
+            let mut user_ids: Vec<&str> = data.as_array().unwrap().iter().map(|entry| entry["userId"].as_str().unwrap()).collect();
+            user_ids.sort();
+            let mut expected = vec![alice.as_str(), carol.as_str()];
+            expected.sort();
+            assert_eq!(user_ids, expected);
+        }
+        assert_ne!(revealed["ballotSimilarity"], 0.0);
+        assert_eq!(anonymous["ballotSimilarity"], 0.0);
     }
 }
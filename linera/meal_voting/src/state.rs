@@ -1,11 +1,14 @@
 // Copyright (c) Kyler
 // SPDX-License-Identifier: Apache-2.0
 
-use async_graphql::SimpleObject;
+use std::collections::BTreeMap;
+
+use async_graphql::{Enum, SimpleObject};
 use linera_sdk::{
-    linera_base_types::ChainId,
-    views::{linera_views, MapView, RegisterView, RootView, ViewStorageContext},
+    linera_base_types::{Account, Amount, ChainId, Timestamp},
+    views::{linera_views, LogView, MapView, RegisterView, RootView, ViewStorageContext},
 };
+use meal_voting::{BallotLimitPolicy, BordaVariant, CategoryBudget, DedupStrategy, TallyMethod};
 use serde::{Deserialize, Serialize};
 
 /// A single nomination (e.g., "Pizza Place").
@@ -13,6 +16,19 @@ use serde::{Deserialize, Serialize};
 pub struct Nomination {
     pub user_id: String,
     pub text: String,
+    /// Head-start points set by the admin, added to the nomination's tally.
+    pub base_score: u64,
+    /// Category this nomination counts against in `category_budgets`, if any.
+    pub category: Option<String>,
+    /// Dietary labels, lowercased (e.g. "vegan", "gluten-free").
+    pub tags: Vec<String>,
+}
+
+/// A participant's comment on a nomination.
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct Comment {
+    pub user_id: String,
+    pub text: String,
 }
 
 /// A nomination entry with its ID (for API responses).
@@ -21,6 +37,34 @@ pub struct NominationEntry {
     pub nomination_id: String,
     pub user_id: String,
     pub text: String,
+    pub endorsement_count: u32,
+    pub base_score: u64,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+}
+
+/// A nomination joined with its result (for API responses).
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct NominationResult {
+    pub nomination_id: String,
+    pub text: String,
+    pub user_id: String,
+    pub score: u64,
+    /// Place in the results, from 1; tied scores share a place.
+    pub rank: u32,
+}
+
+/// Participation figures (for API responses).
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct Turnout {
+    /// Participants who may vote.
+    pub eligible: u32,
+    /// Voters with a ballot on record, abstentions included.
+    pub voted: u32,
+    /// Voters whose ballot is an empty abstention.
+    pub abstained: u32,
+    /// `voted` as a percentage of `eligible` (0 with no participants).
+    pub percentage: f64,
 }
 
 /// A participant entry (for API responses)
@@ -30,12 +74,147 @@ pub struct ParticipantEntry {
     pub name: String,
 }
 
-/// A computed result entry.
+pub use meal_voting::ResultEntry;
+
+/// Lifecycle status of a poll.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, Enum)]
+pub enum PollStatus {
+    /// The poll exists but doesn't accept participation until `opens_at`.
+    Scheduled,
+    /// Participants can join and nominate.
+    Nominating,
+    /// Ballots are being cast.
+    Voting,
+    /// The organizer has temporarily frozen all non-admin activity.
+    Paused,
+    /// The poll is closed and results are final.
+    Closed,
+}
+
+/// Coarse lifecycle phase of a poll, ignoring scheduling and pauses (see `PollStatus`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, Enum)]
+pub enum Phase {
+    /// Participants can join and nominate.
+    Nominating,
+    /// Ballots are being cast.
+    Voting,
+    /// The poll is closed.
+    Closed,
+}
+
+/// A shareable invitation to a poll (for API responses).
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct ShareLink {
+    pub chain_id: ChainId,
+    pub topic: String,
+    pub status: PollStatus,
+    pub invite: String,
+}
+
+/// A tamper-evident record of a poll's outcome (for API responses).
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct ResultsAttestation {
+    pub chain_id: ChainId,
+    pub admin_id: String,
+    pub closed_at: Option<Timestamp>,
+    pub results: Vec<ResultEntry>,
+    /// Hex SHA-256 of the JSON array `[chain_id, admin_id, closed_at, results]`.
+    pub hash: String,
+}
+
+/// How much one voter's ballot moved the winner's margin (for API responses).
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct VoterInfluence {
+    pub user_id: String,
+    /// The winner's margin over the best other option, minus that margin without this ballot.
+    pub influence: i64,
+    /// Whether the winner would no longer win outright without this ballot.
+    pub pivotal: bool,
+}
+
+/// The rank a voter gave the winner: 0 if it was their top choice (for API responses).
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct VoterRegret {
+    pub user_id: String,
+    /// Zero-based position of the winner on the ballot, or the ballot length if unranked.
+    pub regret: u32,
+}
+
+/// Per-voter regret and its average across ballots (for API responses).
 #[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
-pub struct ResultEntry {
+pub struct RegretReport {
+    pub voters: Vec<VoterRegret>,
+    pub average: f64,
+}
+
+/// How many ballots placed a nomination at some position (for API responses).
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct NominationCount {
     pub nomination_id: String,
-    pub nomination_text: String,
-    pub score: u64,
+    pub count: u32,
+}
+
+/// Anonymous per-position ballot counts (for API responses).
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct PositionCounts {
+    /// Zero-based rank position.
+    pub position: u32,
+    pub counts: Vec<NominationCount>,
+}
+
+/// Summary of how many options each ranked ballot lists (for API responses).
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct RankingDepthStats {
+    pub min: u32,
+    pub max: u32,
+    pub mean: f64,
+    pub median: f64,
+}
+
+/// One executed operation, recorded in the audit log.
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct AuditEntry {
+    /// The operation's variant name, e.g. `"Vote"`.
+    pub operation_type: String,
+    pub user_id: String,
+    pub timestamp: Timestamp,
+}
+
+/// A ballot that failed validation and was not counted, kept so the voter can see why.
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct RejectedBallot {
+    pub rankings: Vec<String>,
+    pub reason: String,
+    pub timestamp: Timestamp,
+}
+
+/// App version and headline poll figures for deployment dashboards (for API responses).
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct PollHealth {
+    pub version: String,
+    pub tally_method: TallyMethod,
+    pub phase: Phase,
+    pub participant_count: u32,
+    pub nomination_count: u32,
+}
+
+/// Register-backed poll settings and flags, readable without touching any map (for API responses).
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct PollMeta {
+    pub topic: String,
+    pub admin_id: String,
+    pub votes_per_voter: u32,
+    pub has_started: bool,
+    pub is_closed: bool,
+    pub paused: bool,
+    pub opens_at: Option<Timestamp>,
+    pub closed_at: Option<Timestamp>,
+    pub max_points_per_option: Option<u64>,
+    pub ballot_limit_policy: BallotLimitPolicy,
+    pub ballots_final: bool,
+    pub allow_self_ranking: bool,
+    pub quorum_weight: Option<u64>,
+    pub fee_per_operation: Amount,
 }
 
 /// The application state.
@@ -44,24 +223,148 @@ pub struct ResultEntry {
 pub struct PollState {
     /// The poll topic/question.
     pub topic: RegisterView<String>,
+    /// Longer context for the topic (empty if none).
+    pub description: RegisterView<String>,
+    /// Label for grouping polls (empty if none).
+    pub category: RegisterView<String>,
     /// Number of votes each participant can cast.
     pub votes_per_voter: RegisterView<u32>,
+    /// Per-nomination cap on cumulative-voting points (`None` = no cap).
+    pub max_points_per_option: RegisterView<Option<u64>>,
+    /// How existing ballots are reconciled when `votes_per_voter` is lowered.
+    pub ballot_limit_policy: RegisterView<BallotLimitPolicy>,
+    /// Whether a cast ballot is final (no re-voting).
+    pub ballots_final: RegisterView<bool>,
+    /// When participation opens (`None` = immediately).
+    pub opens_at: RegisterView<Option<Timestamp>>,
+    /// When the decision must be acted on (display only).
+    pub decision_deadline: RegisterView<Option<Timestamp>>,
+    /// Assumed cost of one operation, for fee estimates.
+    pub fee_per_operation: RegisterView<Amount>,
+    /// When the poll closes automatically, in microseconds.
+    pub deadline_micros: RegisterView<Option<u64>>,
+    /// Fewest nominations a non-empty ranked ballot must rank.
+    pub min_rankings: RegisterView<u32>,
+    /// Most nominations each participant may make; 0 means unlimited.
+    pub max_nominations_per_user: RegisterView<u32>,
+    /// Seats filled under STV; 0 means one.
+    pub multi_winner: RegisterView<u32>,
+    /// Whether queries reading ballots or results stay empty until the poll closes.
+    pub hide_ballots_until_close: RegisterView<bool>,
+    /// Point curve for ranked ballots under Borda.
+    pub borda_variant: RegisterView<BordaVariant>,
+    /// Whether ballots may name new options, which are added as nominations by the voter.
+    pub allow_write_ins: RegisterView<bool>,
+    /// Whether queries linking voters to ballots show a salted hash instead of each user ID.
+    pub anonymous_ballots: RegisterView<bool>,
+    /// Per-poll salt for `anonymous_ballots`, derived from public inputs when the poll is created.
+    pub ballot_salt: RegisterView<String>,
+    /// Score at which the poll closes automatically.
+    pub winning_score_threshold: RegisterView<Option<u64>>,
+    /// Ranked ballots needed for the results to be decisive.
+    pub quorum: RegisterView<u32>,
+    /// Whether enough ranked ballots were cast by closing time to meet `quorum`.
+    pub quorum_met: RegisterView<bool>,
+    /// Total voter weight required to close the poll.
+    pub quorum_weight: RegisterView<Option<u64>>,
+    /// Per-category pick allowances for ballots.
+    pub category_budgets: RegisterView<Vec<CategoryBudget>>,
+    /// How duplicate nominations are handled.
+    pub dedup_strategy: RegisterView<DedupStrategy>,
+    /// Whether ballots must rank every active nomination.
+    pub require_full_ranking: RegisterView<bool>,
+    /// Whether voters may rank their own nominations.
+    pub allow_self_ranking: RegisterView<bool>,
+    /// How results are computed when the poll closes.
+    pub tally_method: RegisterView<TallyMethod>,
+    /// Whether invalid ballots are recorded in `rejected_ballots` rather than failing the vote.
+    pub record_rejected_ballots: RegisterView<bool>,
+    /// Most participants admitted, the admin included.
+    pub max_participants: RegisterView<Option<u32>>,
     /// The admin's user ID (chain owner).
     pub admin_id: RegisterView<String>,
     /// Whether voting has started.
     pub has_started: RegisterView<bool>,
+    /// Whether participants may join after voting has started.
+    pub late_join_allowed: RegisterView<bool>,
+    /// Whether non-admin activity is temporarily frozen.
+    pub paused: RegisterView<bool>,
     /// Whether the poll is closed.
     pub is_closed: RegisterView<bool>,
+    /// Whether the results are frozen and the poll accepts no more operations.
+    pub finalized: RegisterView<bool>,
+    /// Whether the admin aborted the poll; it has no results and accepts no more operations.
+    pub is_cancelled: RegisterView<bool>,
+    /// When the poll was closed.
+    pub closed_at: RegisterView<Option<Timestamp>>,
     /// Participants: user_id -> name.
     pub participants: MapView<String, String>,
+    /// Nomination IDs voters may rank; empty means all nominations.
+    pub shortlist: RegisterView<Vec<String>>,
+    /// Voting weights: user_id -> weight (participants not listed weigh 1).
+    pub participant_weights: MapView<String, u64>,
+    /// Numeric suffix of the next `nom_N` nomination ID.
+    pub next_nomination_id: RegisterView<u64>,
     /// Nominations: nomination_id -> Nomination.
     pub nominations: MapView<String, Nomination>,
+    /// Endorsements: nomination_id -> user_ids who endorsed it.
+    pub endorsements: MapView<String, Vec<String>>,
+    /// Comments: nomination_id -> comments in posting order.
+    pub comments: MapView<String, Vec<Comment>>,
     /// Rankings: user_id -> ordered list of nomination_ids.
     pub rankings: MapView<String, Vec<String>>,
+    /// When each user's current ballot was cast.
+    pub ranking_times: MapView<String, Timestamp>,
+    /// How many ballots each user has submitted, counting every revision.
+    pub vote_revisions: MapView<String, u32>,
+    /// Delegations: user_id -> participant whose ballot counts for them if they cast none.
+    pub delegations: MapView<String, String>,
+    /// Rejected ballots: user_id -> ballots that failed validation, oldest first.
+    pub rejected_ballots: MapView<String, Vec<RejectedBallot>>,
+    /// Number of empty ranked ballots, i.e. voters who took part but abstained.
+    pub abstentions: RegisterView<u32>,
+    /// Point allocations: user_id -> (nomination_id, points) pairs.
+    #[graphql(skip)]
+    pub point_allocations: MapView<String, Vec<(String, u64)>>,
+    /// Star ratings: user_id -> (nomination_id, stars 1-5) pairs.
+    #[graphql(skip)]
+    pub star_ratings: MapView<String, Vec<(String, u8)>>,
+    /// Payout accounts: nomination_id -> account credited if that nomination wins.
+    pub payout_addresses: MapView<String, Account>,
     /// Computed results after closing.
     pub results: RegisterView<Vec<ResultEntry>>,
+    /// Results tallied independently per budgeted category: category -> results.
+    pub category_results: MapView<String, Vec<ResultEntry>>,
+    /// Whether "None of the above" won, i.e. no meal was acceptable.
+    pub no_acceptable_option: RegisterView<bool>,
+    /// Nomination ID beating every other one head-to-head at close; `None` on a cycle or tie.
+    pub condorcet_winner: RegisterView<Option<String>>,
+    /// Seed used to order tied results; `None` keeps ties in nomination ID order.
+    pub tie_break_seed: RegisterView<Option<u64>>,
+    /// Every published result set: the original tally followed by each tie-break re-draw.
+    pub results_history: LogView<Vec<ResultEntry>>,
+    /// Factory: last reported results per poll chain.
+    pub poll_results: MapView<ChainId, Vec<ResultEntry>>,
+    /// Coordinator: latest results submitted by each shard chain.
+    pub partial_results: MapView<ChainId, Vec<ResultEntry>>,
+    /// Coordinator: shard chains allowed to submit partial results.
+    pub shard_chains: RegisterView<Vec<ChainId>>,
     /// Factory: user_id -> list of created ChainIds.
     pub created_polls: MapView<String, Vec<ChainId>>,
+    /// Factory: user_id -> poll chains the user joined.
+    pub joined_polls: MapView<String, Vec<ChainId>>,
+    /// Poll: the factory chain that created this poll, notified when users join.
+    pub creator_chain: RegisterView<Option<ChainId>>,
+    /// Number of operations executed on this chain.
+    pub operation_count: RegisterView<u64>,
+    /// Every executed operation, oldest first.
+    pub audit_log: LogView<AuditEntry>,
+    /// Messages received per origin chain in the current throttling window.
+    pub message_counts: MapView<ChainId, u32>,
+    /// Start of the current throttling window, in microseconds.
+    pub message_window_start: RegisterView<u64>,
+    /// Participant messages dropped for exceeding the rate limit.
+    pub dropped_messages: RegisterView<u64>,
 }
 
 /// A ranking entry (user -> list of nomination IDs).
@@ -69,4 +372,148 @@ pub struct PollState {
 pub struct RankingEntry {
     pub user_id: String,
     pub nomination_ids: Vec<String>,
+    /// When the ballot was cast.
+    pub voted_at: Option<Timestamp>,
+    /// How many ballots the user has submitted, counting every revision.
+    pub revisions: u32,
+}
+
+impl PollState {
+    /// Current score per nomination: Borda-like points from ranked ballots, delegated ones
+    /// included (times the voter's weight), plus any cumulative point allocations, star ratings
+    /// and admin-set base scores. `exclude` leaves out the ballots counted for one voter. Scores
+    /// saturate at `u64::MAX` instead of overflowing.
+    pub async fn tally_scores(&self, exclude: Option<&str>) -> BTreeMap<String, u64> {
+        let mut scores: BTreeMap<String, u64> = BTreeMap::new();
+        let max_votes = *self.votes_per_voter.get() as u64;
+        let mut add = |nomination_id: String, points: u64| {
+            let score = scores.entry(nomination_id).or_insert(0);
+            *score = score.saturating_add(points);
+        };
+        let counted = |user_id: &str| Some(user_id) != exclude;
+
+        let nomination_keys = self.nominations.indices().await.expect("indices failed");
+        for nomination_id in nomination_keys {
+            if let Some(nomination) = self.nominations.get(&nomination_id).await.expect("get failed") {
+                if nomination.base_score > 0 {
+                    add(nomination_id, nomination.base_score);
+                }
+            }
+        }
+
+        let variant = *self.borda_variant.get();
+        for (user_id, user_rankings) in self.voter_ballots().await {
+            if !counted(&user_id) {
+                continue;
+            }
+            let weight = self.participant_weights.get(&user_id).await.expect("get failed").unwrap_or(1);
+            for (i, nomination_id) in user_rankings.into_iter().enumerate() {
+                add(nomination_id, variant.points(max_votes, i).saturating_mul(weight));
+            }
+        }
+
+        let allocation_keys = self.point_allocations.indices().await.expect("indices failed");
+        for user_id in allocation_keys.into_iter().filter(|user_id| counted(user_id)) {
+            if let Some(allocations) = self.point_allocations.get(&user_id).await.expect("get failed") {
+                for (nomination_id, points) in allocations {
+                    add(nomination_id, points);
+                }
+            }
+        }
+
+        let rating_keys = self.star_ratings.indices().await.expect("indices failed");
+        for user_id in rating_keys.into_iter().filter(|user_id| counted(user_id)) {
+            if let Some(ratings) = self.star_ratings.get(&user_id).await.expect("get failed") {
+                for (nomination_id, stars) in ratings {
+                    add(nomination_id, stars as u64);
+                }
+            }
+        }
+        scores
+    }
+
+    /// Every counted ballot with the voter it counts for: cast ballots in voter order, then one
+    /// inherited through `delegations` for each delegator who cast none.
+    pub async fn voter_ballots(&self) -> Vec<(String, Vec<String>)> {
+        let mut ballots = Vec::new();
+        let rankings_keys = self.rankings.indices().await.expect("indices failed");
+        for user_id in rankings_keys {
+            if let Some(ballot) = self.rankings.get(&user_id).await.expect("get failed") {
+                ballots.push((user_id, ballot));
+            }
+        }
+        let delegators = self.delegations.indices().await.expect("indices failed");
+        for user_id in delegators {
+            if self.rankings.contains_key(&user_id).await.expect("contains failed") {
+                continue;
+            }
+            if let Some(ballot) = self.delegated_ballot(&user_id).await {
+                ballots.push((user_id, ballot));
+            }
+        }
+        ballots
+    }
+
+    /// The ballot `user_id` inherits by following delegations until someone who voted; `None`
+    /// when the chain ends without a ballot or loops back on itself.
+    async fn delegated_ballot(&self, user_id: &str) -> Option<Vec<String>> {
+        let mut visited = vec![user_id.to_string()];
+        let mut delegate = self.delegations.get(user_id).await.expect("get failed")?;
+        loop {
+            if visited.contains(&delegate) {
+                return None;
+            }
+            if let Some(ballot) = self.rankings.get(&delegate).await.expect("get failed") {
+                return Some(ballot);
+            }
+            let next = self.delegations.get(&delegate).await.expect("get failed")?;
+            visited.push(delegate);
+            delegate = next;
+        }
+    }
+
+    /// Why the top result won, including how often ballots were changed after first being
+    /// cast. `None` until the poll is closed with results.
+    pub async fn winner_explanation(&self) -> Option<String> {
+        if !*self.is_closed.get() {
+            return None;
+        }
+        let winner = self.results.get().first()?;
+        let mut changes = 0u64;
+        let voters = self.vote_revisions.indices().await.expect("indices failed");
+        for user_id in voters {
+            let submissions = self.vote_revisions.get(&user_id).await.expect("get failed").unwrap_or(0);
+            changes += u64::from(submissions.saturating_sub(1));
+        }
+        Some(format!(
+            "{} won with a score of {} ({} first-choice votes); ballots were changed {} times before the close",
+            winner.nomination_text, winner.score, winner.first_choice_votes, changes
+        ))
+    }
+
+    /// The lifecycle phase, derived from `has_started` and `is_closed`.
+    pub fn phase(&self) -> Phase {
+        if *self.is_closed.get() {
+            Phase::Closed
+        } else if *self.has_started.get() {
+            Phase::Voting
+        } else {
+            Phase::Nominating
+        }
+    }
+
+    /// The current lifecycle status at time `now`, derived from the poll flags.
+    pub fn status(&self, now: Timestamp) -> PollStatus {
+        if *self.is_closed.get() {
+            PollStatus::Closed
+        } else if *self.paused.get() {
+            PollStatus::Paused
+        } else if self.opens_at.get().is_some_and(|opens_at| now < opens_at) {
+            PollStatus::Scheduled
+        } else if *self.has_started.get() {
+            PollStatus::Voting
+        } else {
+            PollStatus::Nominating
+        }
+    }
 }